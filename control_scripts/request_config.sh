@@ -0,0 +1,20 @@
+#!/bin/bash
+
+# without a strip length only reads the current configuration
+address="$1"
+num_leds="$2"
+pin="${3:-3}"
+
+script_dir=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+cd "$script_dir"
+
+if [ -z "$num_leds" ]; then
+    curl "$address/config"
+    exit
+fi
+
+sed \
+    -e "s|\$num_leds|$num_leds|g" \
+    -e "s|\$pin|$pin|g" \
+    config_body.json \
+    | curl -H "Content-Type: application/json" -X POST -d @- "$address/config"
@@ -0,0 +1,13 @@
+#!/bin/bash
+
+# deep red at dawn, orange, neutral white, golden in the evening and moonlight blue at night
+current_time="$1"
+address="$2"
+
+script_dir=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+cd "$script_dir"
+
+sed \
+    -e "s|\$current_time|$current_time|g" \
+    keyframes_body.json \
+    | curl -H "Content-Type: application/json" -X POST -d @- "$address"
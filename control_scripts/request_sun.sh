@@ -0,0 +1,17 @@
+#!/bin/bash
+
+# follows the sun at a place given in degrees north and east, the clock of the lamp has to be set
+color="[255, 244, 200]"
+latitude="$1"
+longitude="$2"
+address="$3"
+
+script_dir=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+cd "$script_dir"
+
+sed \
+    -e "s|\$color|$color|g" \
+    -e "s|\$latitude|$latitude|g" \
+    -e "s|\$longitude|$longitude|g" \
+    sun_body.json \
+    | curl -H "Content-Type: application/json" -X POST -d @- "$address"
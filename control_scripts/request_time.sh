@@ -0,0 +1,19 @@
+#!/bin/bash
+
+# without a time only reads the clock of the lamp, "now" sends the time of this machine,
+# an optional POSIX TZ rule sets the time zone of the lamp as well
+address="$1"
+time="$2"
+time_zone="$3"
+
+if [ -z "$time" ]; then
+    curl "$address/time"
+    exit
+fi
+
+if [ "$time" = "now" ]; then
+    time=$(date --iso-8601=seconds)
+fi
+
+curl -H "Content-Type: application/json" -X POST \
+    -d "{\"time\": \"$time\"${time_zone:+, \"time_zone\": \"$time_zone\"}}" "$address/time"
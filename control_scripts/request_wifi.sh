@@ -0,0 +1,9 @@
+#!/bin/bash
+
+# the lamp joins the network after its next restart
+address="$1"
+ssid="$2"
+password="$3"
+
+curl -H "Content-Type: application/json" -X POST \
+    -d "{\"ssid\": \"$ssid\", \"password\": \"$password\"}" "$address/wifi"
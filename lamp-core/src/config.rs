@@ -0,0 +1,489 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use embassy_time::Duration;
+use microjson::{JSONParsingError, JSONValue, JSONValueType};
+
+use crate::{
+    effects::Transition,
+    http::{LedRequest, ParseError, StripTarget, Target},
+    output::BrightnessCurve,
+    settings::LastRequest,
+    types::Color,
+};
+
+pub const MAX_STRIPS: usize = 2;
+pub const MAX_LEDS: usize = 1024;
+// exponents of gamma curves that still leave the midtones visible
+const MIN_GAMMA: f32 = 1.0;
+const MAX_GAMMA: f32 = 4.0;
+
+/// Pulse times of the one-wire protocol of WS281x strips.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timings {
+    /// High and low times of a zero bit in nanosecs.
+    pub t0h: u16,
+    pub t0l: u16,
+    /// High and low times of a one bit in nanosecs.
+    pub t1h: u16,
+    pub t1l: u16,
+    /// Low time that ends a frame in microsecs.
+    pub reset: u16,
+}
+
+impl Timings {
+    pub const WS2813: Self = Self {
+        t0h: 300,
+        t0l: 1000,
+        t1h: 1000,
+        t1l: 300,
+        reset: 300,
+    };
+
+    fn is_valid(&self) -> bool {
+        let pulses_valid = [self.t0h, self.t0l, self.t1h, self.t1l]
+            .iter()
+            .all(|time| (50..=10_000).contains(time));
+        pulses_valid && (50..=800).contains(&self.reset)
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::WS2813
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StripConfig {
+    pub num_leds: usize,
+    /// GPIO number of the data line.
+    pub pin: u8,
+    pub timings: Timings,
+}
+
+/// What the strips show when the lamp powers on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerOn {
+    /// The effect that ran before, cycles stay dark until the clock of the lamp is set
+    /// and then continue in the phase of its time.
+    #[default]
+    Restore,
+    /// Fade to a fixed color.
+    Color(Color),
+    /// Stay dark until the first request.
+    Off,
+}
+
+impl PowerOn {
+    /// Fade from dark at power on.
+    pub const FADE: Duration = Duration::from_secs(10);
+    /// Shown when there is no effect to restore.
+    pub const DEFAULT_COLOR: Color = Color::new(255, 244, 200);
+
+    /// Request to start the strips with, `last` is the request applied before the power loss.
+    /// Cycles lose the time they were sent with, it does not tell how long the power was out.
+    pub fn request(&self, last: Option<LastRequest>) -> Option<LastRequest> {
+        let fixed = |color| LastRequest {
+            target: Target {
+                strip: StripTarget::All,
+                segment: None,
+            },
+            request: LedRequest::Set(color, Self::FADE, Transition::default()),
+        };
+
+        match (self, last) {
+            (PowerOn::Off, _) => None,
+            (PowerOn::Color(color), _) => Some(fixed(*color)),
+            (PowerOn::Restore, None) => Some(fixed(Self::DEFAULT_COLOR)),
+            (PowerOn::Restore, Some(LastRequest { target, request })) => {
+                let request = match request {
+                    // the fade ran before, only its color is left
+                    LedRequest::Set(color, _, transition) => {
+                        LedRequest::Set(color, Self::FADE, transition)
+                    }
+                    LedRequest::DaylightCycle(color, _, ranges, transition) => {
+                        LedRequest::DaylightCycle(color, None, ranges, transition)
+                    }
+                    LedRequest::KeyframeCycle(_, keyframes) => {
+                        LedRequest::KeyframeCycle(None, keyframes)
+                    }
+                    LedRequest::SunCycle(_, sun) => LedRequest::SunCycle(None, sun),
+                };
+                Some(LastRequest { target, request })
+            }
+        }
+    }
+
+    fn parse_json(json: JSONValue) -> Result<Self, ParseError> {
+        match json.value_type {
+            JSONValueType::String => match json.read_string()? {
+                "restore" => Ok(PowerOn::Restore),
+                "off" => Ok(PowerOn::Off),
+                _ => Err(ParseError::ValueError),
+            },
+            _ => Ok(PowerOn::Color(LedRequest::parse_color(
+                json.get_key_value("color")?,
+            )?)),
+        }
+    }
+}
+
+/// Setup of the lamp that can be changed without a new firmware build.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceConfig {
+    pub strips: Vec<StripConfig>,
+    /// Correction applied to the colors of all strips before they are sent.
+    pub brightness_curve: BrightnessCurve,
+    pub power_on: PowerOn,
+}
+
+impl DeviceConfig {
+    /*
+    expected format:
+    {
+        "strips": [
+            {
+                "num_leds": 12,
+                "pin": 3,
+                "timings": {"t0h": 300, "t0l": 1000, "t1h": 1000, "t1l": 300, "reset": 300}
+            }
+        ],
+        "brightness_curve": "cie" | "linear" | {"gamma": 2.2},
+        "power_on": "restore" | "off" | {"color": [255, 244, 200]}
+    }
+    timings are optional and default to WS2813 ones, brightness_curve defaults to "cie",
+    power_on defaults to "restore", its color can be given in any of the formats of LED requests
+    */
+    pub fn parse_json(body: &str) -> Result<Self, ParseError> {
+        let json = JSONValue::load(body);
+
+        let mut strips = Vec::new();
+        for strip in json.get_key_value("strips")?.iter_array()? {
+            if strips.len() == MAX_STRIPS {
+                Err(ParseError::ValueError)?
+            }
+
+            let num_leds = strip.get_key_value("num_leds")?.read_integer()?;
+            let pin = strip.get_key_value("pin")?.read_integer()?;
+            let timings = match strip.get_key_value("timings") {
+                Ok(timings) => Self::parse_timings(timings)?,
+                Err(JSONParsingError::KeyNotFound) => Timings::default(),
+                Err(err) => Err(err)?,
+            };
+
+            let num_leds = usize::try_from(num_leds).map_err(|_| ParseError::ValueError)?;
+            let pin = u8::try_from(pin).map_err(|_| ParseError::ValueError)?;
+            strips.push(StripConfig {
+                num_leds,
+                pin,
+                timings,
+            });
+        }
+
+        let brightness_curve = match json.get_key_value("brightness_curve") {
+            Ok(curve) => Self::parse_brightness_curve(curve)?,
+            Err(JSONParsingError::KeyNotFound) => BrightnessCurve::default(),
+            Err(err) => Err(err)?,
+        };
+        let power_on = match json.get_key_value("power_on") {
+            Ok(power_on) => PowerOn::parse_json(power_on)?,
+            Err(JSONParsingError::KeyNotFound) => PowerOn::default(),
+            Err(err) => Err(err)?,
+        };
+
+        let config = Self {
+            strips,
+            brightness_curve,
+            power_on,
+        };
+        match config.is_valid() {
+            true => Ok(config),
+            false => Err(ParseError::ValueError),
+        }
+    }
+
+    /// Whether the configuration is one [`DeviceConfig::parse_json`] accepts,
+    /// also checked on configurations read back from flash.
+    pub fn is_valid(&self) -> bool {
+        let strips_valid = self
+            .strips
+            .iter()
+            .all(|strip| (1..=MAX_LEDS).contains(&strip.num_leds) && strip.timings.is_valid());
+        // every strip needs its own data line
+        let pins_unique = self
+            .strips
+            .iter()
+            .enumerate()
+            .all(|(i, strip)| self.strips[..i].iter().all(|other| other.pin != strip.pin));
+        let curve_valid = match self.brightness_curve {
+            BrightnessCurve::Gamma(gamma) => (MIN_GAMMA..=MAX_GAMMA).contains(&gamma),
+            _ => true,
+        };
+        (1..=MAX_STRIPS).contains(&self.strips.len()) && strips_valid && pins_unique && curve_valid
+    }
+
+    fn parse_brightness_curve(json: JSONValue) -> Result<BrightnessCurve, ParseError> {
+        match json.value_type {
+            JSONValueType::String => match json.read_string()? {
+                "linear" => Ok(BrightnessCurve::Linear),
+                "cie" => Ok(BrightnessCurve::Cie),
+                _ => Err(ParseError::ValueError),
+            },
+            _ => {
+                let gamma = json.get_key_value("gamma")?.read_float()?;
+                match (MIN_GAMMA..=MAX_GAMMA).contains(&gamma) {
+                    true => Ok(BrightnessCurve::Gamma(gamma)),
+                    false => Err(ParseError::ValueError),
+                }
+            }
+        }
+    }
+
+    fn parse_timings(json: JSONValue) -> Result<Timings, ParseError> {
+        let time = |key| -> Result<u16, ParseError> {
+            let value = json.get_key_value(key)?.read_integer()?;
+            u16::try_from(value).map_err(|_| ParseError::ValueError)
+        };
+        let timings = Timings {
+            t0h: time("t0h")?,
+            t0l: time("t0l")?,
+            t1h: time("t1h")?,
+            t1l: time("t1l")?,
+            reset: time("reset")?,
+        };
+
+        match timings.is_valid() {
+            true => Ok(timings),
+            false => Err(ParseError::ValueError),
+        }
+    }
+
+    /// Serializes the configuration in the format accepted by [`DeviceConfig::parse_json`].
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"strips\": [");
+        for (i, strip) in self.strips.iter().enumerate() {
+            let Timings {
+                t0h,
+                t0l,
+                t1h,
+                t1l,
+                reset,
+            } = strip.timings;
+            let separator = if i == 0 { "" } else { ", " };
+            // writing to a String does not fail
+            let _ = write!(
+                json,
+                "{separator}{{\"num_leds\": {}, \"pin\": {}, \"timings\": {{\"t0h\": {t0h}, \"t0l\": {t0l}, \"t1h\": {t1h}, \"t1l\": {t1l}, \"reset\": {reset}}}}}",
+                strip.num_leds, strip.pin
+            );
+        }
+        json.push_str("], \"brightness_curve\": ");
+        match self.brightness_curve {
+            BrightnessCurve::Linear => json.push_str("\"linear\""),
+            BrightnessCurve::Cie => json.push_str("\"cie\""),
+            BrightnessCurve::Gamma(gamma) => {
+                let _ = write!(json, "{{\"gamma\": {gamma}}}");
+            }
+        }
+        json.push_str(", \"power_on\": ");
+        match self.power_on {
+            PowerOn::Restore => json.push_str("\"restore\""),
+            PowerOn::Off => json.push_str("\"off\""),
+            PowerOn::Color(color) => {
+                let [r, g, b] = color.rgb();
+                let _ = write!(json, "{{\"color\": [{r}, {g}, {b}]}}");
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strips() {
+        let config = DeviceConfig::parse_json(
+            r#"{"strips": [
+                {"num_leds": 30, "pin": 3},
+                {"num_leds": 8, "pin": 5, "timings": {"t0h": 400, "t0l": 850, "t1h": 800, "t1l": 450, "reset": 80}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.strips,
+            [
+                StripConfig {
+                    num_leds: 30,
+                    pin: 3,
+                    timings: Timings::WS2813,
+                },
+                StripConfig {
+                    num_leds: 8,
+                    pin: 5,
+                    timings: Timings {
+                        t0h: 400,
+                        t0l: 850,
+                        t1h: 800,
+                        t1l: 450,
+                        reset: 80,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_json() {
+        let config = DeviceConfig {
+            strips: alloc::vec![StripConfig {
+                num_leds: 12,
+                pin: 3,
+                timings: Timings::WS2813,
+            }],
+            brightness_curve: BrightnessCurve::Gamma(2.2),
+            power_on: PowerOn::Color(Color::new(255, 0, 16)),
+        };
+
+        let json = config.to_json();
+        assert_eq!(
+            json,
+            r#"{"strips": [{"num_leds": 12, "pin": 3, "timings": {"t0h": 300, "t0l": 1000, "t1h": 1000, "t1l": 300, "reset": 300}}], "brightness_curve": {"gamma": 2.2}, "power_on": {"color": [255, 0, 16]}}"#
+        );
+        assert_eq!(DeviceConfig::parse_json(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn rejects_invalid_strips() {
+        for body in [
+            r#"{"strips": []}"#,
+            r#"{"strips": [{"num_leds": 0, "pin": 3}]}"#,
+            r#"{"strips": [{"num_leds": 5000, "pin": 3}]}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 300}]}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3}, {"num_leds": 12, "pin": 3}]}"#,
+            r#"{"strips": [{"num_leds": 1, "pin": 1}, {"num_leds": 1, "pin": 2}, {"num_leds": 1, "pin": 3}]}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3, "timings": {"t0h": 0, "t0l": 1000, "t1h": 1000, "t1l": 300, "reset": 300}}]}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3, "timings": {"t0h": 300, "t0l": 1000, "t1h": 1000, "t1l": 300, "reset": 5000}}]}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3}], "power_on": "on"}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3}], "brightness_curve": "srgb"}"#,
+            r#"{"strips": [{"num_leds": 12, "pin": 3}], "brightness_curve": {"gamma": 0}}"#,
+        ] {
+            assert!(
+                matches!(DeviceConfig::parse_json(body), Err(ParseError::ValueError)),
+                "{body}"
+            );
+        }
+        assert!(matches!(
+            DeviceConfig::parse_json(r#"{"strips": [{"pin": 3}]}"#),
+            Err(ParseError::JsonError(JSONParsingError::KeyNotFound))
+        ));
+    }
+
+    #[test]
+    fn parses_power_on() {
+        let parse = |power_on: &str| {
+            let body = alloc::format!(
+                r#"{{"strips": [{{"num_leds": 12, "pin": 3}}], "power_on": {power_on}}}"#
+            );
+            DeviceConfig::parse_json(&body).unwrap().power_on
+        };
+
+        assert_eq!(parse(r#""off""#), PowerOn::Off);
+        assert_eq!(parse(r#""restore""#), PowerOn::Restore);
+        assert_eq!(
+            parse(r##"{"color": "#FF8000"}"##),
+            PowerOn::Color(Color::new(255, 128, 0))
+        );
+        assert_eq!(
+            DeviceConfig::parse_json(r#"{"strips": [{"num_leds": 12, "pin": 3}]}"#)
+                .unwrap()
+                .power_on,
+            PowerOn::Restore
+        );
+    }
+
+    #[test]
+    fn parses_brightness_curves() {
+        let parse = |curve: &str| {
+            let body = alloc::format!(
+                r#"{{"strips": [{{"num_leds": 12, "pin": 3}}], "brightness_curve": {curve}}}"#
+            );
+            DeviceConfig::parse_json(&body).unwrap().brightness_curve
+        };
+
+        assert_eq!(parse(r#""linear""#), BrightnessCurve::Linear);
+        assert_eq!(parse(r#""cie""#), BrightnessCurve::Cie);
+        assert_eq!(parse(r#"{"gamma": 2.8}"#), BrightnessCurve::Gamma(2.8));
+        assert_eq!(
+            DeviceConfig::parse_json(r#"{"strips": [{"num_leds": 12, "pin": 3}]}"#)
+                .unwrap()
+                .brightness_curve,
+            BrightnessCurve::Cie
+        );
+    }
+
+    fn last(body: &str) -> Option<LastRequest> {
+        Some(LastRequest {
+            target: LedRequest::parse_target(JSONValue::load(body)).unwrap(),
+            request: LedRequest::parse_json(body).unwrap(),
+        })
+    }
+
+    #[test]
+    fn restores_the_last_request() {
+        let cycle = last(
+            r#"{"type": "cycle", "on_color": [255, 244, 200], "current_time": "2014-11-28T21:00:09+09:00",
+                "cycle_minutes": [540, 600, 1260, 1320], "segment": "top"}"#,
+        );
+
+        let Some(LastRequest {
+            target,
+            request: LedRequest::DaylightCycle(_, time, ..),
+        }) = PowerOn::Restore.request(cycle)
+        else {
+            panic!("expected a cycle request");
+        };
+        assert_eq!(target.segment.as_deref(), Some("top"));
+        // the cycle waits for the clock of the lamp
+        assert_eq!(time, None);
+
+        let set = last(r#"{"type": "set", "color": [0, 0, 255], "duration": 600000}"#);
+        let Some(LastRequest {
+            request: LedRequest::Set(color, duration, _),
+            ..
+        }) = PowerOn::Restore.request(set)
+        else {
+            panic!("expected a set request");
+        };
+        assert_eq!((color, duration), (Color::new(0, 0, 255), PowerOn::FADE));
+    }
+
+    #[test]
+    fn applies_fixed_policies() {
+        let cycle = last(
+            r#"{"type": "cycle", "on_color": [255, 244, 200], "current_time": "2014-11-28T21:00:09+09:00",
+                "cycle_minutes": [540, 600, 1260, 1320]}"#,
+        );
+
+        assert!(PowerOn::Off.request(cycle.clone()).is_none());
+        let red = Color::new(255, 0, 0);
+        assert!(matches!(
+            PowerOn::Color(red).request(cycle),
+            Some(LastRequest {
+                request: LedRequest::Set(color, PowerOn::FADE, _),
+                ..
+            }) if color == red
+        ));
+        assert!(matches!(
+            PowerOn::Restore.request(None),
+            Some(LastRequest {
+                request: LedRequest::Set(PowerOn::DEFAULT_COLOR, ..),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,512 @@
+use chrono::{NaiveDate, Utc};
+use embassy_time::Duration;
+
+use super::{EffectEnum, EffectStatus, Keyframes, SunSchedule};
+use crate::effects::MoveTo;
+use crate::types::{Color, clock::Clock, global_time::GlobalInstant, time_zone::TzRule};
+
+use super::Effect;
+
+/// Fades through the colors of its keyframes every day, following the wall time of the lamp.
+pub struct DaylightCycle {
+    keyframes: Keyframes,
+    // computes the keyframes of every day, with the local date of the current ones
+    sun: Option<(SunSchedule, Option<NaiveDate>)>,
+    current_color: Color,
+    state: CycleState,
+    init_effect: Option<MoveTo>,
+}
+
+#[derive(Debug)]
+enum CycleState {
+    // on the way to the color of the next keyframe
+    Fading(MoveTo),
+    // between two keyframes of the same color
+    Holding(Color, Duration),
+    // the wall time of the lamp is not set yet
+    Unknown,
+}
+
+// how often a cycle checks whether the wall time was set
+const UNKNOWN_TIME_POLL: Duration = Duration::from_secs(1);
+// fade from the color a cycle had to the phase of a new wall time
+const RESUME_FADE: Duration = Duration::from_secs(10);
+
+impl DaylightCycle {
+    /// Starts the cycle in the phase of the wall time of `clock`,
+    /// the strip keeps `from_color` until the time is set.
+    pub fn new(from_color: Color, keyframes: Keyframes, clock: &impl Clock) -> Self {
+        let mut cycle = Self {
+            keyframes,
+            sun: None,
+            current_color: from_color,
+            state: CycleState::Unknown,
+            init_effect: None,
+        };
+        cycle.time_changed(clock);
+        cycle
+    }
+
+    /// Starts a cycle that follows the sun of `sun`, with new keyframes every local day.
+    pub fn following_sun(from_color: Color, sun: SunSchedule, clock: &impl Clock) -> Self {
+        let mut cycle = Self {
+            // replaced as soon as the date is known
+            keyframes: sun.keyframes(NaiveDate::default(), &Utc),
+            sun: Some((sun, None)),
+            current_color: from_color,
+            state: CycleState::Unknown,
+            init_effect: None,
+        };
+        cycle.time_changed(clock);
+        cycle
+    }
+
+    // computes the keyframes of a new day, returns whether they changed
+    fn follow_sun(&mut self, now: &GlobalInstant<TzRule>) -> bool {
+        let Some((sun, day)) = &mut self.sun else {
+            return false;
+        };
+        let now = now.datetime();
+        let today = now.date_naive();
+        if *day == Some(today) {
+            return false;
+        }
+
+        *day = Some(today);
+        self.keyframes = sun.keyframes(today, &now.timezone());
+        true
+    }
+
+    /// Moves the cycle to the phase of the wall time of `clock`, fading there from its current color,
+    /// e.g. after the clock of the lamp was set.
+    pub fn time_changed(&mut self, clock: &impl Clock) {
+        let Some(now) = clock.wall_time() else {
+            self.state = CycleState::Unknown;
+            self.init_effect = None;
+            return;
+        };
+        self.follow_sun(&now);
+
+        self.state = self.phase(&now, clock);
+        // where the phase will be once the fade gets there
+        let color = match &self.state {
+            CycleState::Fading(effect) => effect.color_in(clock, RESUME_FADE),
+            CycleState::Holding(color, _) => *color,
+            CycleState::Unknown => self.current_color,
+        };
+        let to = self.keyframes[self.keyframes.minutes().which(now.day_minute())];
+        self.init_effect = Some(MoveTo::new(
+            self.current_color,
+            color,
+            RESUME_FADE,
+            to.transition,
+            clock,
+        ));
+    }
+
+    // the phase of the keyframes at `now`, as far along as if it had run from its start
+    fn phase(&self, now: &GlobalInstant<TzRule>, clock: &impl Clock) -> CycleState {
+        let minutes = self.keyframes.minutes();
+        let index = minutes.which(now.day_minute());
+        let from = self.keyframes[index + self.keyframes.len() - 1];
+        let to = self.keyframes[index];
+        let till_next = now.duration_till_minute(to.minute);
+
+        if from.color == to.color {
+            return CycleState::Holding(to.color, till_next);
+        }
+        // longer than its minutes in the night the clocks are put back
+        let length = Duration::from_secs(minutes.minutes_in(index) * 60).max(till_next);
+        CycleState::Fading(MoveTo::resume(
+            from.color,
+            to.color,
+            length,
+            length - till_next,
+            to.transition,
+            clock,
+        ))
+    }
+
+    fn should_be_state(&self, clock: &impl Clock) -> CycleState {
+        match clock.wall_time() {
+            Some(now) => self.phase(&now, clock),
+            None => CycleState::Unknown,
+        }
+    }
+
+    fn get_color_status(&mut self, clock: &impl Clock) -> (Color, EffectStatus) {
+        if let CycleState::Unknown = self.state {
+            self.time_changed(clock);
+            if let CycleState::Unknown = self.state {
+                return (
+                    self.current_color,
+                    EffectStatus::InProgress(UNKNOWN_TIME_POLL),
+                );
+            }
+        }
+
+        // the sun keeps other times on the new day
+        if let Some(now) = clock.wall_time()
+            && self.follow_sun(&now)
+        {
+            self.state = self.should_be_state(clock);
+        }
+
+        if let Some(effect) = &mut self.init_effect {
+            let (color, status) = effect.step_color(clock);
+            if let EffectStatus::InProgress(_) = status {
+                return (color, status);
+            }
+
+            self.init_effect = None;
+        }
+
+        if let CycleState::Fading(effect) = &mut self.state {
+            let (color, status) = effect.step_color(clock);
+            if let EffectStatus::InProgress(_) = status {
+                return (color, status);
+            }
+        }
+
+        // time to update our state
+        self.state = self.should_be_state(clock);
+
+        let (color, mut status) = match &mut self.state {
+            CycleState::Fading(effect) => effect.step_color(clock),
+            CycleState::Holding(color, duration) => (*color, EffectStatus::InProgress(*duration)),
+            CycleState::Unknown => (
+                self.current_color,
+                EffectStatus::InProgress(UNKNOWN_TIME_POLL),
+            ),
+        };
+
+        if let EffectStatus::Finished = status {
+            status = EffectStatus::InProgress(Duration::from_secs(1));
+        }
+
+        // wake up at midnight for the keyframes of the next day,
+        // right at midnight they are for the day that just started
+        if let (Some(_), Some(now), EffectStatus::InProgress(timeout)) =
+            (&self.sun, clock.wall_time(), &mut status)
+        {
+            let till_midnight = now.duration_till_minute(0);
+            if till_midnight > Duration::from_ticks(0) {
+                *timeout = (*timeout).min(till_midnight);
+            }
+        }
+
+        (color, status)
+    }
+}
+
+impl From<DaylightCycle> for EffectEnum {
+    fn from(effect: DaylightCycle) -> Self {
+        EffectEnum::DaylightCycle(effect)
+    }
+}
+
+impl Effect for DaylightCycle {
+    fn step(&mut self, clock: &impl Clock, frame: &mut [Color]) -> EffectStatus {
+        let (color, status) = self.get_color_status(clock);
+
+        self.current_color = color;
+        frame.fill(color);
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effects::{ColorSpace, Easing, Keyframe, MAX_KEYFRAMES, Transition},
+        types::{clock::ManualClock, ranges::OverlapRanges, sun::Location},
+    };
+
+    const ON: Color = Color::warm_white();
+
+    fn cycle_at(time: &str, clock: &ManualClock) -> DaylightCycle {
+        clock.set_wall_time(time.parse().unwrap());
+        DaylightCycle::new(Color::black(), daylight([540, 600, 1260, 1320]), clock)
+    }
+
+    fn daylight(minutes: [u64; 4]) -> Keyframes {
+        let ranges = OverlapRanges::new(minutes).unwrap();
+        Keyframes::daylight(ON, &ranges, Transition::default())
+    }
+
+    fn step(cycle: &mut DaylightCycle, clock: &ManualClock) -> (Color, EffectStatus) {
+        let mut frame = [Color::black(); 2];
+        let status = cycle.step(clock, &mut frame);
+        (frame[0], status)
+    }
+
+    #[test]
+    fn resumes_mid_rise() {
+        let clock = ManualClock::default();
+        let mut cycle = cycle_at("2025-06-01T09:30:00+02:00", &clock);
+
+        // fade in from the previous color first
+        let (color, _) = step(&mut cycle, &clock);
+        assert_eq!(color, Color::black());
+
+        clock.advance(Duration::from_secs(10));
+        let (color, status) = step(&mut cycle, &clock);
+        // the rise went on during the fade
+        assert_eq!(color, Color::black().interpolate(ON, 30 * 60 + 10, 60 * 60));
+        assert!(matches!(status, EffectStatus::InProgress(_)));
+    }
+
+    #[test]
+    fn resumes_mid_fall() {
+        let clock = ManualClock::default();
+        let mut cycle = cycle_at("2025-06-01T21:15:00+02:00", &clock);
+
+        clock.advance(Duration::from_secs(10));
+        let (color, _) = step(&mut cycle, &clock);
+        assert_eq!(color, ON.interpolate(Color::black(), 15 * 60 + 10, 60 * 60));
+    }
+
+    #[test]
+    fn resumes_on_the_curve_of_the_phase() {
+        for transition in [
+            Transition {
+                easing: Easing::EaseIn,
+                ..Default::default()
+            },
+            Transition {
+                color_space: ColorSpace::Oklab,
+                easing: Easing::Linear,
+            },
+        ] {
+            let clock = ManualClock::default();
+            clock.set_wall_time("2025-06-01T09:30:00+02:00".parse().unwrap());
+            let ranges = OverlapRanges::new([540, 600, 1260, 1320]).unwrap();
+            let keyframes = Keyframes::daylight(ON, &ranges, transition);
+            let mut cycle = DaylightCycle::new(Color::black(), keyframes, &clock);
+
+            // the rise as it runs from 9:00 without interruptions
+            let rise_clock = ManualClock::default();
+            let rise = MoveTo::new(
+                Color::black(),
+                ON,
+                Duration::from_secs(60 * 60),
+                transition,
+                &rise_clock,
+            );
+            let rise_at = |secs| rise.color_in(&rise_clock, Duration::from_secs(secs));
+
+            step(&mut cycle, &clock);
+            clock.advance(RESUME_FADE);
+            assert_eq!(
+                step(&mut cycle, &clock).0,
+                rise_at(30 * 60 + 10),
+                "{transition:?}"
+            );
+            assert_ne!(
+                step(&mut cycle, &clock).0,
+                Color::black().interpolate(ON, 30 * 60 + 10, 60 * 60),
+                "{transition:?}"
+            );
+
+            // and goes on from there instead of starting over
+            clock.advance(Duration::from_secs(15 * 60 - 10));
+            assert_eq!(
+                step(&mut cycle, &clock).0,
+                rise_at(45 * 60),
+                "{transition:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn waits_for_next_phase() {
+        let clock = ManualClock::default();
+        let mut cycle = cycle_at("2025-06-01T12:00:00+02:00", &clock);
+
+        clock.advance(Duration::from_secs(10));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, ON);
+        let EffectStatus::InProgress(timeout) = status else {
+            panic!("the cycle never finishes");
+        };
+        assert_eq!(timeout, Duration::from_secs(9 * 60 * 60 - 10));
+
+        // the fall starts right at 21:00
+        clock.advance(timeout);
+        let (color, _) = step(&mut cycle, &clock);
+        assert_eq!(color, ON);
+        clock.advance(Duration::from_secs(30 * 60));
+        let (color, _) = step(&mut cycle, &clock);
+        assert_eq!(color, ON.interpolate(Color::black(), 1, 2));
+    }
+
+    #[test]
+    fn crosses_midnight() {
+        let clock = ManualClock::default();
+        clock.set_wall_time("2025-06-01T23:30:00+02:00".parse().unwrap());
+        // rising from 22:00 to 23:00, on until 3:00, falling until 4:00
+        let mut cycle =
+            DaylightCycle::new(Color::black(), daylight([1320, 1380, 180, 240]), &clock);
+
+        clock.advance(Duration::from_secs(10));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, ON);
+        let EffectStatus::InProgress(timeout) = status else {
+            panic!("the cycle never finishes");
+        };
+        assert_eq!(timeout, Duration::from_secs((3 * 60 + 30) * 60 - 10));
+
+        // the fall starts the next day
+        clock.advance(timeout);
+        step(&mut cycle, &clock);
+        clock.advance(Duration::from_secs(30 * 60));
+        let (color, _) = step(&mut cycle, &clock);
+        assert_eq!(color, ON.interpolate(Color::black(), 1, 2));
+
+        // and a cycle that resumes after midnight picks up from there
+        clock.set_wall_time("2025-06-02T03:15:00+02:00".parse().unwrap());
+        let mut cycle =
+            DaylightCycle::new(Color::black(), daylight([1320, 1380, 180, 240]), &clock);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            step(&mut cycle, &clock).0,
+            ON.interpolate(Color::black(), 15 * 60 + 10, 60 * 60)
+        );
+    }
+
+    #[test]
+    fn fades_through_keyframes() {
+        let red = Color::new(139, 0, 0);
+        let orange = Color::new(255, 140, 0);
+        let white = Color::from_kelvin(4500, 1.0);
+        let golden = Color::new(255, 200, 80);
+        let blue = Color::new(20, 30, 90);
+        let keyframe = |minute, color, easing| Keyframe {
+            minute,
+            color,
+            transition: Transition {
+                easing,
+                ..Default::default()
+            },
+        };
+        let keyframes = Keyframes::new(&[
+            keyframe(360, red, Easing::Linear),
+            keyframe(420, orange, Easing::Linear),
+            keyframe(600, white, Easing::Linear),
+            keyframe(1140, golden, Easing::Sine),
+            keyframe(1260, blue, Easing::EaseOut),
+        ])
+        .unwrap();
+
+        let clock = ManualClock::default();
+        clock.set_wall_time("2025-06-01T08:00:00+02:00".parse().unwrap());
+        let mut cycle = DaylightCycle::new(Color::black(), keyframes.clone(), &clock);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            step(&mut cycle, &clock).0,
+            orange.interpolate(white, 60 * 60 + 10, 180 * 60)
+        );
+
+        // the fade from the last keyframe to the first one goes through the night
+        clock.set_wall_time("2025-06-01T21:30:00+02:00".parse().unwrap());
+        cycle.time_changed(&clock);
+        clock.advance(Duration::from_secs(10));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, blue.interpolate(red, 30 * 60 + 10, 540 * 60));
+        assert!(matches!(status, EffectStatus::InProgress(_)));
+    }
+
+    #[test]
+    fn needs_ordered_keyframes() {
+        let keyframe = |minute| Keyframe {
+            minute,
+            ..Default::default()
+        };
+
+        assert!(Keyframes::new(&[keyframe(1200), keyframe(360)]).is_ok());
+        assert!(Keyframes::new(&[keyframe(360)]).is_err());
+        assert!(Keyframes::new(&[keyframe(360), keyframe(600), keyframe(480)]).is_err());
+        assert!(Keyframes::new(&[keyframe(0); MAX_KEYFRAMES + 1]).is_err());
+    }
+
+    #[test]
+    fn follows_the_sun_from_day_to_day() {
+        let clock = ManualClock::default();
+        clock.set_time_zone(TzRule::parse("CET-1CEST,M3.5.0,M10.5.0/3"));
+        clock.set_wall_time("2025-06-21T23:00:00+02:00".parse().unwrap());
+        let berlin = SunSchedule {
+            location: Location {
+                latitude: 52.52,
+                longitude: 13.405,
+            },
+            on_color: ON,
+            sunrise_offset: 0,
+            sunset_offset: 0,
+            transition: Transition::default(),
+        };
+        let mut cycle = DaylightCycle::following_sun(Color::black(), berlin, &clock);
+
+        // dark after the dusk, until midnight brings the times of the next day
+        clock.advance(Duration::from_secs(10));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, Color::black());
+        assert_eq!(
+            status,
+            EffectStatus::InProgress(Duration::from_secs(60 * 60 - 10))
+        );
+
+        clock.advance(Duration::from_secs(60 * 60 - 10));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, Color::black());
+        assert_eq!(cycle.sun.unwrap().1, "2025-06-22".parse().ok());
+        // the civil dawn starts before 4:00
+        let EffectStatus::InProgress(timeout) = status else {
+            panic!("the cycle never finishes");
+        };
+        assert!((3 * 60 * 60..4 * 60 * 60).contains(&timeout.as_secs()));
+    }
+
+    #[test]
+    fn follows_the_clock_of_the_lamp() {
+        let clock = ManualClock::default();
+        let mut cycle = cycle_at("2025-06-01T12:00:00+02:00", &clock);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(step(&mut cycle, &clock).0, ON);
+
+        // the clock was corrected to the night, the running cycle fades there
+        clock.set_wall_time("2025-06-01T23:00:00+02:00".parse().unwrap());
+        cycle.time_changed(&clock);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(
+            step(&mut cycle, &clock).0,
+            ON.interpolate(Color::black(), 1, 2)
+        );
+        clock.advance(Duration::from_secs(5));
+        let (color, status) = step(&mut cycle, &clock);
+        assert_eq!(color, Color::black());
+        assert_eq!(
+            status,
+            EffectStatus::InProgress(Duration::from_secs(10 * 60 * 60 - 10))
+        );
+    }
+
+    #[test]
+    fn waits_for_the_time_to_be_set() {
+        let clock = ManualClock::default();
+        let from = Color::new(10, 20, 30);
+        let mut cycle = DaylightCycle::new(from, daylight([540, 600, 1260, 1320]), &clock);
+
+        assert_eq!(
+            step(&mut cycle, &clock),
+            (from, EffectStatus::InProgress(UNKNOWN_TIME_POLL))
+        );
+
+        clock.set_wall_time("2025-06-01T12:00:00+02:00".parse().unwrap());
+        clock.advance(Duration::from_secs(10));
+        step(&mut cycle, &clock);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(step(&mut cycle, &clock).0, ON);
+    }
+}
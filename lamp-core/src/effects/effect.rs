@@ -0,0 +1,60 @@
+use embassy_time::Duration;
+
+use super::{DaylightCycle, Keyframes, MoveTo};
+use crate::{
+    http::LedRequest,
+    types::{Color, clock::Clock},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EffectStatus {
+    InProgress(Duration),
+    Finished,
+}
+
+pub enum EffectEnum {
+    MoveTo(MoveTo),
+    DaylightCycle(DaylightCycle),
+}
+
+pub trait Effect: Into<EffectEnum> {
+    /// Renders the current state of the effect into `frame`, one color per LED.
+    fn step(&mut self, clock: &impl Clock, frame: &mut [Color]) -> EffectStatus;
+}
+
+impl EffectEnum {
+    /// Creates the effect requested by `request`, starting from `from_color`.
+    /// The current time of a cycle request has to be set on `clock` before.
+    pub fn from_request(request: LedRequest, from_color: Color, clock: &impl Clock) -> Self {
+        match request {
+            LedRequest::Set(color, duration, transition) => {
+                MoveTo::new(from_color, color, duration, transition, clock).into()
+            }
+            LedRequest::DaylightCycle(color, _, ranges, transition) => {
+                let keyframes = Keyframes::daylight(color, &ranges, transition);
+                DaylightCycle::new(from_color, keyframes, clock).into()
+            }
+            LedRequest::KeyframeCycle(_, keyframes) => {
+                DaylightCycle::new(from_color, keyframes, clock).into()
+            }
+            LedRequest::SunCycle(_, sun) => {
+                DaylightCycle::following_sun(from_color, sun, clock).into()
+            }
+        }
+    }
+
+    /// Moves effects that follow the time of day to the wall time of `clock` after it was set.
+    pub fn time_changed(&mut self, clock: &impl Clock) {
+        match self {
+            EffectEnum::MoveTo(_) => {}
+            EffectEnum::DaylightCycle(effect) => effect.time_changed(clock),
+        }
+    }
+
+    pub fn step(&mut self, clock: &impl Clock, frame: &mut [Color]) -> EffectStatus {
+        match self {
+            EffectEnum::MoveTo(effect) => effect.step(clock, frame),
+            EffectEnum::DaylightCycle(effect) => effect.step(clock, frame),
+        }
+    }
+}
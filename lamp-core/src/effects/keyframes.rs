@@ -0,0 +1,92 @@
+use alloc::boxed::Box;
+use core::ops::Index;
+
+use super::Transition;
+use crate::types::{
+    Color,
+    ranges::{OverlapRanges, RangesError},
+};
+
+/// Most keyframes a cycle can have.
+pub const MAX_KEYFRAMES: usize = 8;
+
+/// Color a cycle reaches at a minute of the day.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keyframe {
+    pub minute: u64,
+    pub color: Color,
+    /// How the color fades here from the previous keyframe.
+    pub transition: Transition,
+}
+
+/// The keyframes of a day, in order of their minutes, which may wrap around midnight.
+/// The color of the last keyframe of the day fades to the first one of the next day.
+#[derive(Clone)]
+pub struct Keyframes {
+    minutes: OverlapRanges<u64, MAX_KEYFRAMES>,
+    // on the heap, requests and effects are moved around a lot
+    frames: Box<[Keyframe; MAX_KEYFRAMES]>,
+}
+
+impl Keyframes {
+    /// Takes 2 to [`MAX_KEYFRAMES`] keyframes at different minutes.
+    pub fn new(keyframes: &[Keyframe]) -> Result<Self, RangesError> {
+        if !(2..=MAX_KEYFRAMES).contains(&keyframes.len()) {
+            Err(RangesError)?
+        }
+
+        let mut minutes = [0; MAX_KEYFRAMES];
+        let mut frames = Box::new([Keyframe::default(); MAX_KEYFRAMES]);
+        for (i, keyframe) in keyframes.iter().enumerate() {
+            minutes[i] = keyframe.minute;
+            frames[i] = *keyframe;
+        }
+
+        Ok(Self {
+            minutes: OverlapRanges::from_slice(&minutes[..keyframes.len()])?,
+            frames,
+        })
+    }
+
+    /// The sunrise and sunset of `ranges`: dark until the first minute, `on_color` from the second
+    /// to the third and dark again from the fourth, both fades shaped by `transition`.
+    pub fn daylight(
+        on_color: Color,
+        ranges: &OverlapRanges<u64, 4>,
+        transition: Transition,
+    ) -> Self {
+        let colors = [Color::black(), on_color, on_color, Color::black()];
+        let keyframes: [Keyframe; 4] = core::array::from_fn(|i| Keyframe {
+            minute: ranges[i],
+            color: colors[i],
+            transition,
+        });
+
+        Self::new(&keyframes).expect("because the ranges are well ordered")
+    }
+
+    pub fn minutes(&self) -> &OverlapRanges<u64, MAX_KEYFRAMES> {
+        &self.minutes
+    }
+
+    pub fn len(&self) -> usize {
+        self.minutes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.minutes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Keyframe> {
+        self.frames[..self.len()].iter()
+    }
+}
+
+impl Index<usize> for Keyframes {
+    type Output = Keyframe;
+
+    /// Wraps around like the ranges of the minutes.
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.frames[index % self.len()]
+    }
+}
@@ -0,0 +1,17 @@
+mod effect;
+pub use effect::{Effect, EffectEnum, EffectStatus};
+
+mod transition;
+pub use transition::{ColorSpace, Easing, Transition};
+
+mod move_to;
+pub use move_to::MoveTo;
+
+mod keyframes;
+pub use keyframes::{Keyframe, Keyframes, MAX_KEYFRAMES};
+
+mod sun_schedule;
+pub use sun_schedule::SunSchedule;
+
+mod daylight_cycle;
+pub use daylight_cycle::DaylightCycle;
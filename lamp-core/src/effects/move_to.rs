@@ -0,0 +1,394 @@
+use core::iter::zip;
+
+use embassy_time::{Duration, Instant};
+
+use super::{ColorSpace, Easing, Effect, EffectEnum, EffectStatus, Transition};
+use crate::types::{Color, clock::Clock, oklab::Oklab};
+
+// resolution of eased progress when blending in RGB
+const PROGRESS_STEPS: u64 = 1 << 24;
+
+#[derive(Debug)]
+pub struct MoveTo {
+    from: Color,
+    to: Color,
+    t0: Instant,
+    // millis of the transition that passed before t0
+    skipped: u64,
+    duration: u64,
+    easing: Easing,
+    // endpoints converted for blending in OKLab
+    lab: Option<(Oklab, Oklab)>,
+}
+
+impl From<MoveTo> for EffectEnum {
+    fn from(effect: MoveTo) -> Self {
+        EffectEnum::MoveTo(effect)
+    }
+}
+
+impl MoveTo {
+    pub fn new(
+        from: Color,
+        to: Color,
+        duration: Duration,
+        transition: Transition,
+        clock: &impl Clock,
+    ) -> Self {
+        let lab = match transition.color_space {
+            ColorSpace::Rgb => None,
+            ColorSpace::Oklab => Some((from.into(), to.into())),
+        };
+
+        Self {
+            from,
+            to,
+            t0: clock.now(),
+            skipped: 0,
+            duration: duration.as_millis(),
+            easing: transition.easing,
+            lab,
+        }
+    }
+
+    /// Same as [`MoveTo::new`] for a transition that started `elapsed` ago.
+    pub fn resume(
+        from: Color,
+        to: Color,
+        duration: Duration,
+        elapsed: Duration,
+        transition: Transition,
+        clock: &impl Clock,
+    ) -> Self {
+        Self {
+            skipped: elapsed.as_millis(),
+            ..Self::new(from, to, duration, transition, clock)
+        }
+    }
+
+    /// Color of the transition `later` from now.
+    pub fn color_in(&self, clock: &impl Clock, later: Duration) -> Color {
+        self.color_at(self.millis(clock) + later.as_millis())
+    }
+
+    fn millis(&self, clock: &impl Clock) -> u64 {
+        (clock.now() - self.t0).as_millis() + self.skipped
+    }
+
+    fn is_linear(&self) -> bool {
+        self.lab.is_none() && self.easing == Easing::Linear
+    }
+
+    fn color_at(&self, millis: u64) -> Color {
+        if millis >= self.duration {
+            return self.to;
+        }
+        if millis == 0 {
+            return self.from;
+        }
+        if self.is_linear() {
+            return self.from.interpolate(self.to, millis, self.duration);
+        }
+
+        let progress = self.easing.apply(millis as f32 / self.duration as f32);
+        match &self.lab {
+            None => self.from.interpolate(
+                self.to,
+                (progress * PROGRESS_STEPS as f32) as u64,
+                PROGRESS_STEPS,
+            ),
+            Some((from, to)) => from.interpolate(*to, progress).into(),
+        }
+    }
+
+    pub fn millis_till_update(&self, current_millis: u64) -> Option<u64> {
+        if self.is_linear() {
+            self.linear_millis_till_update(current_millis)
+        } else {
+            self.search_millis_till_update(current_millis)
+        }
+    }
+
+    fn linear_millis_till_update(&self, current_millis: u64) -> Option<u64> {
+        let current_color = self
+            .from
+            .interpolate(self.to, current_millis, self.duration);
+        let mut next_update: Option<u64> = None;
+
+        for ((from, to), current) in
+            zip(self.from.rgb16(), self.to.rgb16()).zip(current_color.rgb16())
+        {
+            let from = from as i32;
+            let to = to as i32;
+            let current = current as i32;
+
+            let sign = (to - from).signum();
+            if sign == 0 || current == to {
+                continue;
+            }
+
+            // The update threshold is different when going up and when going down
+            // due to integer division rounding down.
+            let target = current + (sign + 1) / 2;
+
+            let mut wait_time = ((target - from) * sign) as u64;
+            wait_time *= self.duration;
+            wait_time /= ((to - from) * sign) as u64;
+
+            // Check that at time wait_time we indeed get the next color.
+            // If not then the integer division inside interpolate introduces an off by one error.
+            let next_at_wait_time = ((from as u64 * (self.duration - wait_time)
+                + to as u64 * wait_time)
+                / self.duration) as i32;
+            if current + sign != next_at_wait_time {
+                wait_time += 1
+            }
+
+            wait_time -= current_millis;
+
+            if let Some(time) = next_update {
+                if time > wait_time {
+                    next_update = Some(wait_time);
+                }
+            } else {
+                next_update = Some(wait_time);
+            }
+        }
+
+        next_update
+    }
+
+    // Finds the first millisecond at which the color differs from the current one,
+    // for paths that are not linear in every channel.
+    fn search_millis_till_update(&self, current_millis: u64) -> Option<u64> {
+        let current_color = self.color_at(current_millis);
+        if current_millis >= self.duration || current_color == self.to {
+            return None;
+        }
+        let remaining = self.duration - current_millis;
+
+        // probe in growing steps first, then bisect the step where the color changed
+        let mut low = 0;
+        let mut high = 1;
+        while high < remaining && self.color_at(current_millis + high) == current_color {
+            low = high;
+            high = (high * 2).min(remaining);
+        }
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if self.color_at(current_millis + mid) == current_color {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Some(high)
+    }
+}
+
+impl MoveTo {
+    /// Computes the color of the whole strip at the current time.
+    pub fn step_color(&mut self, clock: &impl Clock) -> (Color, EffectStatus) {
+        let dt = self.millis(clock);
+        if dt >= self.duration {
+            return (self.to, EffectStatus::Finished);
+        }
+
+        let current_color = self.color_at(dt);
+
+        // when is the next update?
+        let status = self
+            .millis_till_update(dt)
+            .map(Duration::from_millis)
+            .map(EffectStatus::InProgress)
+            .unwrap_or(EffectStatus::Finished);
+
+        (current_color, status)
+    }
+}
+
+impl Effect for MoveTo {
+    fn step(&mut self, clock: &impl Clock, frame: &mut [Color]) -> EffectStatus {
+        let (color, status) = self.step_color(clock);
+        frame.fill(color);
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::clock::ManualClock;
+
+    fn color_at(effect: &MoveTo, millis: u64) -> Color {
+        effect.color_at(millis)
+    }
+
+    fn assert_updates_on_every_step(
+        from: Color,
+        to: Color,
+        duration: Duration,
+        transition: Transition,
+    ) {
+        let effect = MoveTo::new(from, to, duration, transition, &ManualClock::default());
+
+        let mut millis = 0;
+        while let Some(wait) = effect.millis_till_update(millis) {
+            assert!(wait > 0, "update scheduled without waiting at {millis} ms");
+            // the color stays the same until the scheduled update...
+            assert_eq!(
+                color_at(&effect, millis + wait - 1),
+                color_at(&effect, millis)
+            );
+            // ...and changes right at it
+            assert_ne!(color_at(&effect, millis + wait), color_at(&effect, millis));
+            millis += wait;
+        }
+
+        assert_eq!(color_at(&effect, millis), to);
+    }
+
+    #[test]
+    fn schedules_every_color_step_going_up() {
+        assert_updates_on_every_step(
+            Color::black(),
+            Color::new(255, 244, 200),
+            Duration::from_secs(10),
+            Transition::default(),
+        );
+    }
+
+    #[test]
+    fn schedules_every_color_step_going_down() {
+        assert_updates_on_every_step(
+            Color::new(255, 244, 200),
+            Color::new(3, 0, 7),
+            Duration::from_millis(4321),
+            Transition::default(),
+        );
+    }
+
+    #[test]
+    fn schedules_every_color_step_in_oklab() {
+        let oklab = Transition {
+            color_space: ColorSpace::Oklab,
+            ..Default::default()
+        };
+        assert_updates_on_every_step(
+            Color::new(255, 244, 200),
+            Color::new(0, 20, 120),
+            Duration::from_secs(3),
+            oklab,
+        );
+        assert_updates_on_every_step(
+            Color::black(),
+            Color::new(255, 0, 0),
+            Duration::from_secs(30 * 60),
+            oklab,
+        );
+    }
+
+    #[test]
+    fn schedules_every_color_step_with_easing() {
+        for easing in [
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Sine,
+            Easing::Cubic,
+            Easing::Smoothstep,
+        ] {
+            assert_updates_on_every_step(
+                Color::black(),
+                Color::new(255, 244, 200),
+                Duration::from_secs(10),
+                Transition {
+                    easing,
+                    ..Default::default()
+                },
+            );
+        }
+        assert_updates_on_every_step(
+            Color::new(255, 244, 200),
+            Color::new(0, 20, 120),
+            Duration::from_secs(5),
+            Transition {
+                color_space: ColorSpace::Oklab,
+                easing: Easing::Sine,
+            },
+        );
+    }
+
+    #[test]
+    fn eases_progress() {
+        let from = Color::black();
+        let to = Color::new(200, 0, 0);
+        let effect = |easing| {
+            MoveTo::new(
+                from,
+                to,
+                Duration::from_secs(10),
+                Transition {
+                    easing,
+                    ..Default::default()
+                },
+                &ManualClock::default(),
+            )
+        };
+
+        assert_eq!(effect(Easing::EaseIn).color_at(5_000).rgb(), [50, 0, 0]);
+        assert_eq!(effect(Easing::EaseOut).color_at(5_000).rgb(), [150, 0, 0]);
+        assert_eq!(
+            effect(Easing::Smoothstep).color_at(5_000).rgb(),
+            [100, 0, 0]
+        );
+        assert_eq!(effect(Easing::Cubic).color_at(10_000), to);
+    }
+
+    #[test]
+    fn oklab_keeps_endpoints() {
+        let clock = ManualClock::default();
+        let from = Color::new(255, 244, 200);
+        let to = Color::new(0, 20, 120);
+        let effect = MoveTo::new(
+            from,
+            to,
+            Duration::from_secs(10),
+            Transition {
+                color_space: ColorSpace::Oklab,
+                ..Default::default()
+            },
+            &clock,
+        );
+
+        assert_eq!(effect.color_at(0), from);
+        assert_eq!(effect.color_at(10_000), to);
+        assert_ne!(effect.color_at(5_000), from.interpolate(to, 1, 2));
+    }
+
+    #[test]
+    fn follows_the_clock() {
+        let clock = ManualClock::default();
+        let mut effect = MoveTo::new(
+            Color::black(),
+            Color::new(100, 0, 0),
+            Duration::from_secs(10),
+            Transition::default(),
+            &clock,
+        );
+        let mut frame = [Color::black(); 3];
+
+        clock.advance(Duration::from_millis(2500));
+        let status = effect.step(&clock, &mut frame);
+        assert!(frame.iter().all(|color| color.rgb() == [25, 0, 0]));
+        assert!(matches!(status, EffectStatus::InProgress(d) if d == Duration::from_millis(1)));
+
+        clock.advance(Duration::from_secs(10));
+        assert!(matches!(
+            effect.step(&clock, &mut frame),
+            EffectStatus::Finished
+        ));
+        assert!(frame.iter().all(|color| color.rgb() == [100, 0, 0]));
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone, Timelike, Utc};
+
+use super::{Keyframe, Keyframes, Transition};
+use crate::types::{
+    Color,
+    sun::{CIVIL_TWILIGHT_ALTITUDE, Crossing, Location, SUNRISE_ALTITUDE},
+};
+
+/// Daylight cycle that follows the sun at a place: the light rises through the civil dawn
+/// until sunrise and falls from sunset through the civil dusk, both moved by their offsets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunSchedule {
+    pub location: Location,
+    pub on_color: Color,
+    /// Minutes the rise is moved by, e.g. 30 to rise half an hour after the sun.
+    pub sunrise_offset: i16,
+    /// Minutes the fall is moved by.
+    pub sunset_offset: i16,
+    pub transition: Transition,
+}
+
+impl SunSchedule {
+    /// Keyframes of `date` in the local time of `zone`.
+    ///
+    /// Without a civil dusk the light falls until the middle of the night. It stays on on polar days
+    /// and off on polar nights, and also when the offsets move the rise past the fall.
+    pub fn keyframes<Tz: TimeZone>(&self, date: NaiveDate, zone: &Tz) -> Keyframes {
+        let keyframe = |time: DateTime<Utc>, offset: i16, color| Keyframe {
+            minute: ((time + TimeDelta::minutes(offset.into()))
+                .with_timezone(zone)
+                .num_seconds_from_midnight()
+                / 60)
+                .into(),
+            color,
+            transition: self.transition,
+        };
+        let (on, off) = (self.on_color, Color::black());
+        let (rise, fall) = (self.sunrise_offset, self.sunset_offset);
+
+        let keyframes = match (
+            self.location.crossing(date, SUNRISE_ALTITUDE),
+            self.location.crossing(date, CIVIL_TWILIGHT_ALTITUDE),
+        ) {
+            (Crossing::Between(sunrise, sunset), Crossing::Between(dawn, dusk)) => {
+                Keyframes::new(&[
+                    keyframe(dawn, rise, off),
+                    keyframe(sunrise, rise, on),
+                    keyframe(sunset, fall, on),
+                    keyframe(dusk, fall, off),
+                ])
+            }
+            (Crossing::Between(sunrise, sunset), _) => {
+                let midnight = self.location.noon(date) - TimeDelta::hours(12);
+                Keyframes::new(&[
+                    keyframe(midnight, 0, off),
+                    keyframe(sunrise, rise, on),
+                    keyframe(sunset, fall, on),
+                ])
+            }
+            (Crossing::Above, _) => Ok(all_day(on)),
+            (Crossing::Below, _) => Ok(all_day(off)),
+        };
+        keyframes.unwrap_or_else(|_| all_day(off))
+    }
+}
+
+fn all_day(color: Color) -> Keyframes {
+    let keyframe = |minute| Keyframe {
+        minute,
+        color,
+        transition: Transition::default(),
+    };
+    Keyframes::new(&[keyframe(0), keyframe(12 * 60)]).expect("because the minutes differ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::time_zone::TzRule;
+
+    fn berlin(sunrise_offset: i16, sunset_offset: i16) -> SunSchedule {
+        SunSchedule {
+            location: Location {
+                latitude: 52.52,
+                longitude: 13.405,
+            },
+            on_color: Color::warm_white(),
+            sunrise_offset,
+            sunset_offset,
+            transition: Transition::default(),
+        }
+    }
+
+    fn minutes(keyframes: &Keyframes) -> alloc::vec::Vec<u64> {
+        keyframes.iter().map(|keyframe| keyframe.minute).collect()
+    }
+
+    #[test]
+    fn follows_the_sun_in_local_time() {
+        let cet = TzRule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let date = "2025-06-21".parse().unwrap();
+
+        // dawn 3:5x, sunrise 4:43, sunset 21:33, dusk 22:2x in summer time
+        let keyframes = berlin(0, 0).keyframes(date, &cet);
+        let [dawn, sunrise, sunset, dusk] = minutes(&keyframes)[..] else {
+            panic!("expected four keyframes");
+        };
+        assert!((230..240).contains(&dawn));
+        assert!((282..285).contains(&sunrise));
+        assert!((1292..1295).contains(&sunset));
+        assert!((1340..1350).contains(&dusk));
+        assert_eq!(keyframes[0].color, Color::black());
+        assert_eq!(keyframes[1].color, Color::warm_white());
+
+        let moved = berlin(30, -60).keyframes(date, &cet);
+        assert_eq!(
+            minutes(&moved),
+            [dawn + 30, sunrise + 30, sunset - 60, dusk - 60]
+        );
+    }
+
+    #[test]
+    fn falls_until_midnight_without_a_dusk() {
+        let trondheim = SunSchedule {
+            location: Location {
+                latitude: 63.43,
+                longitude: 10.40,
+            },
+            ..berlin(0, 0)
+        };
+
+        let keyframes = trondheim.keyframes("2025-06-21".parse().unwrap(), &Utc);
+        assert_eq!(keyframes.len(), 3);
+        assert_eq!(keyframes[0].color, Color::black());
+        // the sun is lowest at about 23:20 UTC
+        assert!((1390..1410).contains(&keyframes[0].minute));
+    }
+
+    #[test]
+    fn stays_on_or_off_near_the_poles() {
+        let tromso = SunSchedule {
+            location: Location {
+                latitude: 69.65,
+                longitude: 18.96,
+            },
+            ..berlin(0, 0)
+        };
+
+        let summer = tromso.keyframes("2025-06-21".parse().unwrap(), &Utc);
+        assert!(
+            summer
+                .iter()
+                .all(|keyframe| keyframe.color == Color::warm_white())
+        );
+        let winter = tromso.keyframes("2025-12-21".parse().unwrap(), &Utc);
+        assert!(
+            winter
+                .iter()
+                .all(|keyframe| keyframe.color == Color::black())
+        );
+
+        // offsets that leave no day
+        let keyframes = berlin(240, -240).keyframes("2025-12-21".parse().unwrap(), &Utc);
+        assert!(
+            keyframes
+                .iter()
+                .all(|keyframe| keyframe.color == Color::black())
+        );
+    }
+}
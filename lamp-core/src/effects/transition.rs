@@ -0,0 +1,102 @@
+use core::f32::consts::PI;
+
+/// Color space the colors of a transition are blended in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Every channel is blended on its own, cheap but passes through grays
+    /// between distant hues.
+    #[default]
+    Rgb,
+    /// Perceptual blending, hue and lightness change evenly.
+    Oklab,
+}
+
+/// Shape of the progress of a transition over its duration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Quadratic, starts slow.
+    EaseIn,
+    /// Quadratic, ends slow.
+    EaseOut,
+    /// Quadratic, starts and ends slow.
+    EaseInOut,
+    /// Half a cosine wave, starts and ends slow.
+    Sine,
+    /// Cubic, starts and ends slower than [`Easing::EaseInOut`].
+    Cubic,
+    Smoothstep,
+}
+
+impl Easing {
+    /// Maps linear progress in `0.0..=1.0` to eased progress in `0.0..=1.0`.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let eased = match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Easing::EaseInOut => 1.0 - 2.0 * (1.0 - t) * (1.0 - t),
+            Easing::Sine => (1.0 - libm::cosf(PI * t)) / 2.0,
+            Easing::Cubic if t < 0.5 => 4.0 * t * t * t,
+            Easing::Cubic => 1.0 - 4.0 * (1.0 - t) * (1.0 - t) * (1.0 - t),
+            Easing::Smoothstep => t * t * (3.0 - 2.0 * t),
+        };
+        eased.clamp(0.0, 1.0)
+    }
+}
+
+/// How [`MoveTo`](super::MoveTo) gets from one color to the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Transition {
+    pub color_space: ColorSpace,
+    pub easing: Easing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 7] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::Sine,
+        Easing::Cubic,
+        Easing::Smoothstep,
+    ];
+
+    #[test]
+    fn keeps_endpoints() {
+        for easing in ALL {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn never_goes_back() {
+        for easing in ALL {
+            let samples = (0..=1000).map(|i| easing.apply(i as f32 / 1000.0));
+            assert!(samples.is_sorted(), "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn shapes_progress() {
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+        for easing in [
+            Easing::EaseInOut,
+            Easing::Sine,
+            Easing::Cubic,
+            Easing::Smoothstep,
+        ] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-6, "{easing:?}");
+            assert!(easing.apply(0.1) < 0.1, "{easing:?}");
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use httparse::Status;
+use microjson::JSONValue;
+
+use super::{LedRequest, ParseError, Target, TimeRequest};
+use crate::{config::DeviceConfig, settings::WifiCredentials};
+
+/// Request to one of the endpoints of the lamp.
+pub enum ApiRequest {
+    /// `POST /`: start an effect
+    Led(Target, LedRequest),
+    /// `GET /config`: read the hardware configuration
+    GetConfig,
+    /// `POST /config`: change the hardware configuration
+    SetConfig(DeviceConfig),
+    /// `POST /wifi`: change the network joined after the next restart
+    SetWifi(WifiCredentials),
+    /// `GET /time`: read the clock of the lamp
+    GetTime,
+    /// `POST /time`: set the clock or the time zone of the lamp
+    SetTime(TimeRequest),
+}
+
+impl ApiRequest {
+    pub fn parse_http(buffer: &[u8]) -> Result<Self, ParseError> {
+        // parse HTTP headers
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut req = httparse::Request::new(&mut headers);
+        let header_end = if let Status::Complete(n) = req.parse(buffer)? {
+            n
+        } else {
+            Err(httparse::Error::Status)?
+        };
+
+        let body = core::str::from_utf8(&buffer[header_end..])?;
+        let path = req.path.unwrap_or("/");
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+        match (req.method.unwrap_or("POST"), path) {
+            ("GET", "/config") => Ok(Self::GetConfig),
+            ("POST", "/config") => Ok(Self::SetConfig(DeviceConfig::parse_json(body)?)),
+            ("POST", "/wifi") => Ok(Self::SetWifi(WifiCredentials::parse_json(body)?)),
+            ("GET", "/time") => Ok(Self::GetTime),
+            ("POST", "/time") => Ok(Self::SetTime(TimeRequest::parse_json(body)?)),
+            // LED requests were accepted on any path before there were other endpoints
+            ("POST", _) => {
+                let json = JSONValue::load(body);
+                Ok(Self::Led(
+                    LedRequest::parse_target(json)?,
+                    LedRequest::from_json(json)?,
+                ))
+            }
+            _ => Err(ParseError::NotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_config_requests() {
+        assert!(matches!(
+            ApiRequest::parse_http(b"GET /config HTTP/1.1\r\nHost: lamp\r\n\r\n"),
+            Ok(ApiRequest::GetConfig)
+        ));
+
+        let buffer = b"POST /config HTTP/1.1\r\nHost: lamp\r\n\r\n\
+            {\"strips\": [{\"num_leds\": 30, \"pin\": 3}]}";
+        let Ok(ApiRequest::SetConfig(config)) = ApiRequest::parse_http(buffer) else {
+            panic!("expected a config request");
+        };
+        assert_eq!(config.strips[0].num_leds, 30);
+    }
+
+    #[test]
+    fn routes_wifi_requests() {
+        let buffer = b"POST /wifi HTTP/1.1\r\nHost: lamp\r\n\r\n\
+            {\"ssid\": \"home\", \"password\": \"secret\"}";
+        let Ok(ApiRequest::SetWifi(credentials)) = ApiRequest::parse_http(buffer) else {
+            panic!("expected a wifi request");
+        };
+        assert_eq!(credentials.ssid, "home");
+        assert_eq!(credentials.password, "secret");
+
+        let buffer = b"POST /wifi HTTP/1.1\r\nHost: lamp\r\n\r\n\
+            {\"ssid\": \"a network name longer than 32 bytes\", \"password\": \"\"}";
+        assert!(matches!(
+            ApiRequest::parse_http(buffer),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn routes_time_requests() {
+        assert!(matches!(
+            ApiRequest::parse_http(b"GET /time HTTP/1.1\r\nHost: lamp\r\n\r\n"),
+            Ok(ApiRequest::GetTime)
+        ));
+
+        let buffer = b"POST /time HTTP/1.1\r\nHost: lamp\r\n\r\n\
+            {\"epoch\": 1748757600, \"offset\": 7200}";
+        let Ok(ApiRequest::SetTime(request)) = ApiRequest::parse_http(buffer) else {
+            panic!("expected a time request");
+        };
+        assert_eq!(request.time.unwrap().timestamp(), 1748757600);
+        assert_eq!(request.time_zone, None);
+    }
+
+    #[test]
+    fn rejects_unknown_endpoints() {
+        assert!(matches!(
+            ApiRequest::parse_http(b"GET / HTTP/1.1\r\nHost: lamp\r\n\r\n"),
+            Err(ParseError::NotFound)
+        ));
+        assert!(matches!(
+            ApiRequest::parse_http(b"DELETE /config HTTP/1.1\r\nHost: lamp\r\n\r\n"),
+            Err(ParseError::NotFound)
+        ));
+    }
+}
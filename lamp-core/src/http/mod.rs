@@ -0,0 +1,14 @@
+mod api_request;
+pub use api_request::ApiRequest;
+
+mod parse_error;
+pub use parse_error::ParseError;
+
+mod request;
+pub use request::{LedRequest, StripTarget, Target};
+
+mod response_builder;
+pub use response_builder::ResponseBuilder;
+
+mod time;
+pub use time::{TimeRequest, time_to_json};
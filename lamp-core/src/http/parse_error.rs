@@ -5,6 +5,9 @@ pub enum ParseError {
     JsonError(microjson::JSONParsingError),
     ChronoError(chrono::ParseError),
     ValueError,
+    // more than MAX_KEYFRAMES keyframes in a keyframes request
+    TooManyKeyframes,
+    NotFound,
 }
 
 impl From<httparse::Error> for ParseError {
@@ -0,0 +1,720 @@
+use alloc::string::{String, ToString};
+
+use chrono::{DateTime, FixedOffset};
+use embassy_time::Duration;
+use microjson::{JSONParsingError, JSONValue, JSONValueType};
+
+use super::parse_error::ParseError;
+use crate::{
+    effects::{ColorSpace, Easing, Keyframe, Keyframes, MAX_KEYFRAMES, SunSchedule, Transition},
+    types::{
+        Color,
+        ranges::{DAY_MINUTES, OverlapRanges},
+        sun::Location,
+    },
+};
+
+#[derive(Clone)]
+pub enum LedRequest {
+    Set(Color, Duration, Transition),
+    /// The current time is `None` when the request relies on the clock of the lamp.
+    DaylightCycle(
+        Color,
+        Option<DateTime<FixedOffset>>,
+        OverlapRanges<u64, 4>,
+        Transition,
+    ),
+    /// A cycle through the colors of a day, with the current time like [`LedRequest::DaylightCycle`].
+    KeyframeCycle(Option<DateTime<FixedOffset>>, Keyframes),
+    /// A cycle that follows the sun, with the current time like [`LedRequest::DaylightCycle`].
+    SunCycle(Option<DateTime<FixedOffset>>, SunSchedule),
+}
+
+/// LED strips a request applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StripTarget {
+    All,
+    Only(usize),
+}
+
+impl StripTarget {
+    pub fn includes(&self, strip: usize) -> bool {
+        match self {
+            StripTarget::All => true,
+            StripTarget::Only(index) => *index == strip,
+        }
+    }
+
+    /// Whether the target exists on a lamp with `num_strips` strips.
+    pub fn fits(&self, num_strips: usize) -> bool {
+        match self {
+            StripTarget::All => true,
+            StripTarget::Only(index) => *index < num_strips,
+        }
+    }
+}
+
+/// Part of the lamp a request applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub strip: StripTarget,
+    /// Name of a segment of the strips, the whole strips when `None`.
+    pub segment: Option<String>,
+}
+
+impl LedRequest {
+    /*
+    optional fields of every request:
+        "strip": 1  (index of the strip, all strips when missing)
+        "segment": "top"  (name of a segment, the whole strips when missing)
+    */
+    pub fn parse_target(json: JSONValue) -> Result<Target, ParseError> {
+        let strip = match json.get_key_value("strip") {
+            Ok(strip) => {
+                let index = strip.read_integer()?;
+                if index < 0 {
+                    Err(ParseError::ValueError)?
+                }
+                StripTarget::Only(index as usize)
+            }
+            Err(JSONParsingError::KeyNotFound) => StripTarget::All,
+            Err(err) => Err(err)?,
+        };
+        let segment = match json.get_key_value("segment") {
+            Ok(segment) => Some(segment.read_string()?.to_string()),
+            Err(JSONParsingError::KeyNotFound) => None,
+            Err(err) => Err(err)?,
+        };
+
+        Ok(Target { strip, segment })
+    }
+
+    /// Whether the request can only run once the clock of the lamp is set.
+    pub fn needs_time(&self) -> bool {
+        matches!(
+            self,
+            LedRequest::DaylightCycle(_, None, ..)
+                | LedRequest::KeyframeCycle(None, _)
+                | LedRequest::SunCycle(None, _)
+        )
+    }
+
+    /// The time a cycle request sets the clock of the lamp to.
+    pub fn current_time(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            LedRequest::Set(..) => None,
+            LedRequest::DaylightCycle(_, time, ..)
+            | LedRequest::KeyframeCycle(time, _)
+            | LedRequest::SunCycle(time, _) => *time,
+        }
+    }
+
+    pub fn parse_json(body: &str) -> Result<LedRequest, ParseError> {
+        Self::from_json(JSONValue::load(body))
+    }
+
+    /// Request of a body that was already loaded, e.g. to read its target too.
+    pub fn from_json(json: JSONValue) -> Result<LedRequest, ParseError> {
+        let request = match json.get_key_value("type")?.read_string()? {
+            "set" => {
+                /*
+                expected format:
+                {
+                    "type": "set",
+                    "color": [255, 244, 200],
+                    "duration": 10000,
+                    "color_space": "oklab",
+                    "easing": "ease_in_out"
+                }
+                colors can be given in any of the formats accepted by parse_color,
+                color_space and easing are optional, see parse_transition
+                */
+                let color = Self::parse_color(json.get_key_value("color")?)?;
+                let duration =
+                    Duration::from_millis(json.get_key_value("duration")?.read_integer()? as u64);
+
+                let transition = Self::parse_transition(&json)?;
+
+                Self::Set(color, duration, transition)
+            }
+            "cycle" => {
+                /*
+                expected format:
+                {
+                    "type": "cycle",
+                    "on_color": [255, 244, 200],
+                    "current_time": "2014-11-28T21:00:09+09:00",
+                    "cycle_minutes": [540, 600, 1260, 1320],
+                    "color_space": "oklab",
+                    "easing": "sine"
+                }
+                color_space and easing are optional, see parse_transition,
+                the easing shapes both the rising and the falling phase,
+                current_time is optional once the lamp knows the time, it sets the clock of the lamp
+                */
+                let on_color = Self::parse_color(json.get_key_value("on_color")?)?;
+                let current_time = Self::parse_current_time(&json)?;
+
+                let mut minutes_iter = json.get_key_value("cycle_minutes")?.iter_array()?;
+                let mut minutes: [u64; 4] = [0; 4];
+                minutes
+                    .iter_mut()
+                    .try_for_each(|m| -> Result<(), ParseError> {
+                        *m =
+                            Self::parse_minute(minutes_iter.next().ok_or(ParseError::ValueError)?)?;
+                        Ok(())
+                    })?;
+
+                // make sure minutes are well ordered (checked in OverlapRanges::new)
+                let ranges = OverlapRanges::new(minutes)?;
+
+                let transition = Self::parse_transition(&json)?;
+
+                Self::DaylightCycle(on_color, current_time, ranges, transition)
+            }
+            "keyframes" => {
+                /*
+                expected format:
+                {
+                    "type": "keyframes",
+                    "current_time": "2014-11-28T21:00:09+09:00",
+                    "keyframes": [
+                        {"minute": 360, "color": "#8B0000"},
+                        {"minute": 420, "color": [255, 140, 0], "easing": "sine"},
+                        {"minute": 600, "color": {"kelvin": 4500}},
+                        {"minute": 1140, "color": {"kelvin": 2200}, "color_space": "oklab"},
+                        {"minute": 1260, "color": [20, 30, 90]}
+                    ]
+                }
+                the lamp shows the color of a keyframe at its minute of the day, fading there
+                from the previous keyframe as given by the color_space and easing of the keyframe,
+                see parse_transition, the last keyframe fades to the first one,
+                current_time is optional like in a cycle request,
+                there are 2 to MAX_KEYFRAMES keyframes, more are a TooManyKeyframes error
+                */
+                let current_time = Self::parse_current_time(&json)?;
+
+                let mut keyframes = [Keyframe::default(); MAX_KEYFRAMES];
+                let mut len = 0;
+                for keyframe in json.get_key_value("keyframes")?.iter_array()? {
+                    let slot = keyframes.get_mut(len).ok_or(ParseError::TooManyKeyframes)?;
+                    *slot = Keyframe {
+                        minute: Self::parse_minute(keyframe.get_key_value("minute")?)?,
+                        color: Self::parse_color(keyframe.get_key_value("color")?)?,
+                        transition: Self::parse_transition(&keyframe)?,
+                    };
+                    len += 1;
+                }
+
+                // make sure minutes are well ordered (checked in Keyframes::new)
+                Self::KeyframeCycle(current_time, Keyframes::new(&keyframes[..len])?)
+            }
+            "sun" => {
+                /*
+                expected format:
+                {
+                    "type": "sun",
+                    "on_color": [255, 244, 200],
+                    "latitude": 52.52,
+                    "longitude": 13.405,
+                    "sunrise_offset": 30,
+                    "sunset_offset": -15,
+                    "current_time": "2014-11-28T21:00:09+09:00",
+                    "color_space": "oklab",
+                    "easing": "sine"
+                }
+                latitude and longitude are in degrees north and east, the light rises through
+                the civil dawn until sunrise and falls from sunset through the civil dusk,
+                the offsets move the rise and the fall by up to 3 hours and default to 0,
+                current_time, color_space and easing are optional like in a cycle request
+                */
+                let on_color = Self::parse_color(json.get_key_value("on_color")?)?;
+                let current_time = Self::parse_current_time(&json)?;
+
+                let latitude = json.get_key_value("latitude")?.read_float()?;
+                let longitude = json.get_key_value("longitude")?.read_float()?;
+                if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                    Err(ParseError::ValueError)?
+                }
+
+                let sun = SunSchedule {
+                    location: Location {
+                        latitude,
+                        longitude,
+                    },
+                    on_color,
+                    sunrise_offset: Self::parse_offset(&json, "sunrise_offset")?,
+                    sunset_offset: Self::parse_offset(&json, "sunset_offset")?,
+                    transition: Self::parse_transition(&json)?,
+                };
+
+                Self::SunCycle(current_time, sun)
+            }
+            _ => Err(ParseError::ValueError)?,
+        };
+
+        Ok(request)
+    }
+
+    /*
+    accepted formats:
+        [255, 244, 200]
+        "#FFF4C8"
+        {"kelvin": 2700, "brightness": 0.8}  (brightness is optional)
+        {"h": 40, "s": 0.2, "v": 1.0}
+    */
+    pub(crate) fn parse_color(val: JSONValue) -> Result<Color, ParseError> {
+        match val.value_type {
+            JSONValueType::Array => {
+                let mut iter = val.iter_array()?;
+                let r = iter.next().ok_or(ParseError::ValueError)?.read_integer()? as u8;
+                let g = iter.next().ok_or(ParseError::ValueError)?.read_integer()? as u8;
+                let b = iter.next().ok_or(ParseError::ValueError)?.read_integer()? as u8;
+                Ok(Color::new(r, g, b))
+            }
+            JSONValueType::String => {
+                Color::from_hex(val.read_string()?).ok_or(ParseError::ValueError)
+            }
+            JSONValueType::Object => match val.get_key_value("kelvin") {
+                Ok(kelvin) => {
+                    let kelvin = kelvin.read_integer()?;
+                    let brightness = match val.get_key_value("brightness") {
+                        Ok(brightness) => Self::parse_unit(brightness)?,
+                        Err(JSONParsingError::KeyNotFound) => 1.0,
+                        Err(err) => Err(err)?,
+                    };
+                    if !(1000..=40000).contains(&kelvin) {
+                        Err(ParseError::ValueError)?
+                    }
+                    Ok(Color::from_kelvin(kelvin as u32, brightness))
+                }
+                Err(JSONParsingError::KeyNotFound) => {
+                    let hue = val.get_key_value("h")?.read_float()?;
+                    let saturation = Self::parse_unit(val.get_key_value("s")?)?;
+                    let value = Self::parse_unit(val.get_key_value("v")?)?;
+                    Ok(Color::from_hsv(hue, saturation, value))
+                }
+                Err(err) => Err(err)?,
+            },
+            _ => Err(ParseError::ValueError),
+        }
+    }
+
+    // optional in cycle requests, sets the clock of the lamp
+    fn parse_current_time(json: &JSONValue) -> Result<Option<DateTime<FixedOffset>>, ParseError> {
+        match json.get_key_value("current_time") {
+            Ok(time) => Ok(Some(time.read_string()?.parse()?)),
+            Err(JSONParsingError::KeyNotFound) => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    // reads a minute of the day, phases may cross midnight, which may also be given as 1440
+    fn parse_minute(val: JSONValue) -> Result<u64, ParseError> {
+        let minute = val.read_integer()?;
+        if !(0..=DAY_MINUTES as isize).contains(&minute) {
+            Err(ParseError::ValueError)?
+        }
+        Ok(minute as u64 % DAY_MINUTES)
+    }
+
+    // reads optional minutes to move a phase of a sun cycle by
+    fn parse_offset(json: &JSONValue, key: &str) -> Result<i16, ParseError> {
+        let offset = match json.get_key_value(key) {
+            Ok(offset) => offset.read_integer()?,
+            Err(JSONParsingError::KeyNotFound) => 0,
+            Err(err) => Err(err)?,
+        };
+        if !(-180..=180).contains(&offset) {
+            Err(ParseError::ValueError)?
+        }
+        Ok(offset as i16)
+    }
+
+    /*
+    optional fields shared by all fading requests:
+        "color_space": "rgb" | "oklab"  (defaults to "rgb")
+        "easing": "linear" | "ease_in" | "ease_out" | "ease_in_out" | "sine" | "cubic" | "smoothstep"
+            (defaults to "linear")
+    */
+    fn parse_transition(json: &JSONValue) -> Result<Transition, ParseError> {
+        let color_space = match json.get_key_value("color_space") {
+            Ok(color_space) => match color_space.read_string()? {
+                "rgb" => ColorSpace::Rgb,
+                "oklab" => ColorSpace::Oklab,
+                _ => Err(ParseError::ValueError)?,
+            },
+            Err(JSONParsingError::KeyNotFound) => ColorSpace::default(),
+            Err(err) => Err(err)?,
+        };
+
+        let easing = match json.get_key_value("easing") {
+            Ok(easing) => match easing.read_string()? {
+                "linear" => Easing::Linear,
+                "ease_in" => Easing::EaseIn,
+                "ease_out" => Easing::EaseOut,
+                "ease_in_out" => Easing::EaseInOut,
+                "sine" => Easing::Sine,
+                "cubic" => Easing::Cubic,
+                "smoothstep" => Easing::Smoothstep,
+                _ => Err(ParseError::ValueError)?,
+            },
+            Err(JSONParsingError::KeyNotFound) => Easing::default(),
+            Err(err) => Err(err)?,
+        };
+
+        Ok(Transition {
+            color_space,
+            easing,
+        })
+    }
+
+    // reads a number in the 0.0..=1.0 range
+    fn parse_unit(val: JSONValue) -> Result<f32, ParseError> {
+        let value = val.read_float()?;
+        if !(0.0..=1.0).contains(&value) {
+            Err(ParseError::ValueError)?
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::ApiRequest;
+
+    #[test]
+    fn parses_set_request() {
+        let buffer = b"POST / HTTP/1.1\r\nHost: lamp\r\nContent-Type: application/json\r\n\r\n\
+            {\"type\": \"set\", \"color\": [255, 244, 200], \"duration\": 10000}";
+
+        let Ok(ApiRequest::Led(target, LedRequest::Set(color, duration, transition))) =
+            ApiRequest::parse_http(buffer)
+        else {
+            panic!("expected a set request");
+        };
+        assert_eq!(
+            target,
+            Target {
+                strip: StripTarget::All,
+                segment: None
+            }
+        );
+        assert_eq!(color, Color::new(255, 244, 200));
+        assert_eq!(duration, Duration::from_secs(10));
+        assert_eq!(transition, Transition::default());
+    }
+
+    #[test]
+    fn parses_strip_target() {
+        let buffer = b"POST / HTTP/1.1\r\nHost: lamp\r\n\r\n\
+            {\"type\": \"set\", \"color\": [255, 244, 200], \"duration\": 10000, \"strip\": 1}";
+
+        let Ok(ApiRequest::Led(target, LedRequest::Set(..))) = ApiRequest::parse_http(buffer)
+        else {
+            panic!("expected a set request");
+        };
+        let target = target.strip;
+        assert_eq!(target, StripTarget::Only(1));
+        assert!(target.includes(1) && !target.includes(0));
+        assert!(target.fits(2) && !target.fits(1));
+        assert!(StripTarget::All.includes(5) && StripTarget::All.fits(1));
+
+        assert!(matches!(
+            LedRequest::parse_target(JSONValue::load(r#"{"type": "set", "strip": -1}"#)),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn parses_segment_target() {
+        let target =
+            LedRequest::parse_target(JSONValue::load(r#"{"type": "set", "segment": "top"}"#))
+                .unwrap();
+        assert_eq!(target.strip, StripTarget::All);
+        assert_eq!(target.segment.as_deref(), Some("top"));
+
+        assert!(matches!(
+            LedRequest::parse_target(JSONValue::load(r#"{"type": "set", "segment": 3}"#)),
+            Err(ParseError::JsonError(_))
+        ));
+    }
+
+    #[test]
+    fn parses_cycle_request() {
+        let body = r#"{
+            "type": "cycle",
+            "on_color": [255, 244, 200],
+            "current_time": "2014-11-28T21:00:09+09:00",
+            "cycle_minutes": [540, 600, 1260, 1320]
+        }"#;
+
+        let Ok(LedRequest::DaylightCycle(color, time, ranges, _)) = LedRequest::parse_json(body)
+        else {
+            panic!("expected a cycle request");
+        };
+        assert_eq!(color, Color::new(255, 244, 200));
+        assert_eq!(
+            time,
+            "2014-11-28T21:00:09+09:00"
+                .parse::<DateTime<FixedOffset>>()
+                .ok()
+        );
+        assert_eq!(
+            [ranges[0], ranges[1], ranges[2], ranges[3]],
+            [540, 600, 1260, 1320]
+        );
+    }
+
+    #[test]
+    fn parses_cycle_without_time() {
+        let body = r#"{"type": "cycle", "on_color": [255, 244, 200], "cycle_minutes": [540, 600, 1260, 1320]}"#;
+
+        let request = LedRequest::parse_json(body).unwrap();
+        assert!(matches!(request, LedRequest::DaylightCycle(_, None, ..)));
+        assert!(request.needs_time());
+        let set = LedRequest::parse_json(r#"{"type": "set", "color": [0, 0, 0], "duration": 0}"#);
+        assert!(!set.unwrap().needs_time());
+    }
+
+    fn parse_set_color(color: &str) -> Result<Color, ParseError> {
+        let body = format!(r#"{{"type": "set", "color": {color}, "duration": 1000}}"#);
+        match LedRequest::parse_json(&body)? {
+            LedRequest::Set(color, _, _) => Ok(color),
+            _ => panic!("expected a set request"),
+        }
+    }
+
+    #[test]
+    fn parses_color_formats() {
+        assert_eq!(
+            parse_set_color(r##""#FFF4C8""##).unwrap(),
+            Color::new(255, 244, 200)
+        );
+        assert_eq!(
+            parse_set_color(r#"{"kelvin": 2700, "brightness": 0.8}"#).unwrap(),
+            Color::from_kelvin(2700, 0.8)
+        );
+        assert_eq!(
+            parse_set_color(r#"{"kelvin": 6600}"#).unwrap(),
+            Color::from_kelvin(6600, 1.0)
+        );
+        assert_eq!(
+            parse_set_color(r#"{"h": 120, "s": 1, "v": 0.5}"#).unwrap(),
+            Color::from_hsv(120.0, 1.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        for color in [
+            r##""#FFF4""##,
+            r#"{"kelvin": 500}"#,
+            r#"{"kelvin": 2700, "brightness": 1.5}"#,
+            r#"{"h": 120, "s": 1.5, "v": 1}"#,
+            "12",
+        ] {
+            assert!(
+                matches!(parse_set_color(color), Err(ParseError::ValueError)),
+                "{color}"
+            );
+        }
+        assert!(matches!(
+            parse_set_color(r#"{"h": 120, "s": 1}"#),
+            Err(ParseError::JsonError(JSONParsingError::KeyNotFound))
+        ));
+    }
+
+    #[test]
+    fn parses_color_space() {
+        let body =
+            r#"{"type": "set", "color": [0, 0, 255], "duration": 1000, "color_space": "oklab"}"#;
+        let Ok(LedRequest::Set(_, _, transition)) = LedRequest::parse_json(body) else {
+            panic!("expected a set request");
+        };
+        assert_eq!(transition.color_space, ColorSpace::Oklab);
+
+        let body = r#"{
+            "type": "cycle",
+            "on_color": [255, 244, 200],
+            "current_time": "2014-11-28T21:00:09+09:00",
+            "cycle_minutes": [540, 600, 1260, 1320],
+            "color_space": "rgb"
+        }"#;
+        let Ok(LedRequest::DaylightCycle(.., transition)) = LedRequest::parse_json(body) else {
+            panic!("expected a cycle request");
+        };
+        assert_eq!(transition.color_space, ColorSpace::Rgb);
+
+        assert!(matches!(
+            LedRequest::parse_json(
+                r#"{"type": "set", "color": [0, 0, 255], "duration": 1000, "color_space": "hsl"}"#
+            ),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn parses_easing() {
+        let body =
+            r#"{"type": "set", "color": [0, 0, 255], "duration": 1000, "easing": "ease_in_out"}"#;
+        let Ok(LedRequest::Set(_, _, transition)) = LedRequest::parse_json(body) else {
+            panic!("expected a set request");
+        };
+        assert_eq!(transition.easing, Easing::EaseInOut);
+        assert_eq!(transition.color_space, ColorSpace::Rgb);
+
+        assert!(matches!(
+            LedRequest::parse_json(
+                r#"{"type": "set", "color": [0, 0, 255], "duration": 1000, "easing": "bounce"}"#
+            ),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn rejects_unordered_cycle_minutes() {
+        let body = r#"{
+            "type": "cycle",
+            "on_color": [255, 244, 200],
+            "current_time": "2014-11-28T21:00:09+09:00",
+            "cycle_minutes": [540, 1260, 600, 1320]
+        }"#;
+
+        assert!(matches!(
+            LedRequest::parse_json(body),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn parses_cycles_across_midnight() {
+        let body = r#"{
+            "type": "cycle",
+            "on_color": [255, 244, 200],
+            "cycle_minutes": [1320, 1380, 1440, 240]
+        }"#;
+
+        let Ok(LedRequest::DaylightCycle(_, _, ranges, _)) = LedRequest::parse_json(body) else {
+            panic!("expected a cycle request");
+        };
+        assert_eq!([ranges[2], ranges[3]], [0, 240]);
+        assert_eq!(ranges.which(30), 3);
+    }
+
+    #[test]
+    fn parses_keyframe_cycles() {
+        let body = r##"{
+            "type": "keyframes",
+            "current_time": "2014-11-28T21:00:09+09:00",
+            "keyframes": [
+                {"minute": 360, "color": "#8B0000"},
+                {"minute": 420, "color": [255, 140, 0], "easing": "sine"},
+                {"minute": 1260, "color": [20, 30, 90], "color_space": "oklab"}
+            ]
+        }"##;
+
+        let request = LedRequest::parse_json(body).unwrap();
+        assert_eq!(
+            request.current_time(),
+            "2014-11-28T21:00:09+09:00".parse().ok()
+        );
+        let LedRequest::KeyframeCycle(_, keyframes) = request else {
+            panic!("expected a keyframe cycle request");
+        };
+        assert_eq!(keyframes.len(), 3);
+        assert_eq!(keyframes[0].color, Color::new(139, 0, 0));
+        assert_eq!(keyframes[1].transition.easing, Easing::Sine);
+        assert_eq!(keyframes[2].transition.color_space, ColorSpace::Oklab);
+        assert_eq!(keyframes.minutes().which(1400), 0);
+
+        let body = r#"{"type": "keyframes", "keyframes": [{"minute": 360, "color": [1, 2, 3]}]}"#;
+        assert!(matches!(
+            LedRequest::parse_json(body),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn parses_sun_cycles() {
+        let body = r#"{"type": "sun", "on_color": [255, 244, 200], "latitude": -33.87, "longitude": 151.21,
+            "sunrise_offset": 30, "easing": "sine"}"#;
+
+        let request = LedRequest::parse_json(body).unwrap();
+        assert!(request.needs_time());
+        let LedRequest::SunCycle(_, sun) = request else {
+            panic!("expected a sun cycle request");
+        };
+        assert_eq!(sun.location.latitude, -33.87);
+        assert_eq!(sun.location.longitude, 151.21);
+        assert_eq!((sun.sunrise_offset, sun.sunset_offset), (30, 0));
+        assert_eq!(sun.transition.easing, Easing::Sine);
+
+        for body in [
+            r#"{"type": "sun", "on_color": [1, 2, 3], "latitude": 91, "longitude": 0}"#,
+            r#"{"type": "sun", "on_color": [1, 2, 3], "latitude": 0, "longitude": -181}"#,
+            r#"{"type": "sun", "on_color": [1, 2, 3], "latitude": 0, "longitude": 0, "sunset_offset": 200}"#,
+        ] {
+            assert!(matches!(
+                LedRequest::parse_json(body),
+                Err(ParseError::ValueError)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_too_many_keyframes() {
+        let keyframes = (0..=MAX_KEYFRAMES)
+            .map(|i| alloc::format!(r#"{{"minute": {}, "color": [1, 2, 3]}}"#, i * 60))
+            .collect::<alloc::vec::Vec<_>>()
+            .join(", ");
+        let body = alloc::format!(r#"{{"type": "keyframes", "keyframes": [{keyframes}]}}"#);
+
+        assert!(matches!(
+            LedRequest::parse_json(&body),
+            Err(ParseError::TooManyKeyframes)
+        ));
+    }
+
+    #[test]
+    fn rejects_cycle_minutes_past_midnight() {
+        let body = r#"{
+            "type": "cycle",
+            "on_color": [255, 244, 200],
+            "current_time": "2014-11-28T21:00:09+09:00",
+            "cycle_minutes": [540, 600, 1260, 1500]
+        }"#;
+
+        assert!(matches!(
+            LedRequest::parse_json(body),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_type_and_bad_fields() {
+        assert!(matches!(
+            LedRequest::parse_json(r#"{"type": "blink"}"#),
+            Err(ParseError::ValueError)
+        ));
+        assert!(matches!(
+            LedRequest::parse_json(r#"{"type": "set", "color": [1, 2], "duration": 5}"#),
+            Err(ParseError::ValueError)
+        ));
+        assert!(matches!(
+            LedRequest::parse_json(
+                r#"{"type": "cycle", "on_color": [1, 2, 3], "current_time": "noon", "cycle_minutes": [1, 2, 3, 4]}"#
+            ),
+            Err(ParseError::ChronoError(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_incomplete_http() {
+        assert!(matches!(
+            ApiRequest::parse_http(b"POST / HTTP/1.1\r\nHost: lamp\r\n"),
+            Err(ParseError::HttpError(_))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use super::ParseError;
+use crate::effects::MAX_KEYFRAMES;
+
+pub struct ResponseBuilder<'a> {
+    buffer: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> ResponseBuilder<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, pos: 0 }
+    }
+
+    pub fn add(&mut self, text: &str) -> &mut Self {
+        let text = text.as_bytes();
+        let len = text.len();
+        self.buffer[self.pos..self.pos + len].copy_from_slice(text);
+        self.pos += len;
+        self
+    }
+
+    pub fn build_response(&mut self) -> &[u8] {
+        self.build_json_response("{\"response\": \"OK\"}")
+    }
+
+    /// Builds a successful response with a JSON body.
+    pub fn build_json_response(&mut self, contents: &str) -> &[u8] {
+        self.pos = 0;
+
+        let status_line = "HTTP/1.1 200 OK";
+
+        self.add(status_line)
+            .add("\r\n")
+            .add("Content-Length: ")
+            .add(itoa::Buffer::new().format(contents.len()))
+            .add("\r\n")
+            .add("Content-Type: application/json\r\n")
+            .add("\r\n")
+            .add(contents)
+            .add("\r\n");
+
+        &self.buffer[..self.pos]
+    }
+
+    pub fn build_bad_request(&mut self, error: ParseError) -> &[u8] {
+        self.pos = 0;
+
+        let status_line = match error {
+            ParseError::NotFound => "HTTP/1.1 404 Not Found",
+            _ => "HTTP/1.1 400 Bad Request",
+        };
+        let contents_begin = "{\"response\": \"";
+
+        let contents_explanation = match error {
+            ParseError::HttpError(_) => "Invalid HTTP request",
+            ParseError::Utf8Error(_) => "Invalid UTF-8",
+            ParseError::JsonError(_) => "Invalid JSON",
+            ParseError::ChronoError(_) => "Invalid DateTime",
+            ParseError::ValueError => "Invalid values in request",
+            ParseError::TooManyKeyframes => "Too many keyframes, a cycle takes up to ",
+            ParseError::NotFound => "Unknown endpoint",
+        };
+        // the limit is written from the constant the parser checks against
+        let mut limit = itoa::Buffer::new();
+        let contents_limit = match error {
+            ParseError::TooManyKeyframes => limit.format(MAX_KEYFRAMES),
+            _ => "",
+        };
+        let contents_end = "\"}";
+
+        let contents_len = contents_begin.len()
+            + contents_explanation.len()
+            + contents_limit.len()
+            + contents_end.len();
+
+        self.add(status_line)
+            .add("\r\n")
+            .add("Content-Length: ")
+            .add(itoa::Buffer::new().format(contents_len))
+            .add("\r\n")
+            .add("Content-Type: application/json\r\n")
+            .add("\r\n")
+            .add(contents_begin)
+            .add(contents_explanation)
+            .add(contents_limit)
+            .add(contents_end)
+            .add("\r\n");
+
+        &self.buffer[..self.pos]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_length_matches_body() {
+        let mut buffer = [0; 256];
+        let mut builder = ResponseBuilder::new(&mut buffer);
+
+        let response =
+            core::str::from_utf8(builder.build_bad_request(ParseError::ValueError)).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+
+        assert!(head.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(head.contains("Content-Length: 41"));
+        assert_eq!(body, "{\"response\": \"Invalid values in request\"}\r\n");
+    }
+
+    #[test]
+    fn reports_unknown_endpoints() {
+        let mut buffer = [0; 256];
+        let mut builder = ResponseBuilder::new(&mut buffer);
+
+        let response =
+            core::str::from_utf8(builder.build_bad_request(ParseError::NotFound)).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        assert!(response.ends_with("{\"response\": \"Unknown endpoint\"}\r\n"));
+    }
+
+    #[test]
+    fn names_the_keyframe_limit() {
+        let mut buffer = [0; 256];
+        let mut builder = ResponseBuilder::new(&mut buffer);
+
+        let response =
+            core::str::from_utf8(builder.build_bad_request(ParseError::TooManyKeyframes)).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        let limit = alloc::format!("up to {MAX_KEYFRAMES}\"");
+        assert!(response.contains(&limit));
+    }
+}
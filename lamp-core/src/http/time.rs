@@ -0,0 +1,180 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Timelike};
+use microjson::{JSONParsingError, JSONValue};
+
+use super::ParseError;
+use crate::settings::LocalTimeZone;
+
+/// Body of `POST /time`, a new time, a new time zone or both.
+pub struct TimeRequest {
+    pub time: Option<DateTime<FixedOffset>>,
+    pub time_zone: Option<LocalTimeZone>,
+}
+
+impl TimeRequest {
+    /*
+    expected format, either
+        {"time": "2025-06-01T08:00:00+02:00"}
+    or the Unix time in seconds and the UTC offset in seconds east of Greenwich
+        {"epoch": 1748757600, "offset": 7200}
+    where the offset is optional and defaults to UTC,
+    optionally with a POSIX TZ rule, "" to go back to the offset of the time
+        {"time_zone": "CET-1CEST,M3.5.0,M10.5.0/3"}
+    */
+    pub fn parse_json(body: &str) -> Result<Self, ParseError> {
+        let json = JSONValue::load(body);
+
+        let time_zone = match json.get_key_value("time_zone") {
+            Ok(rule) => {
+                Some(LocalTimeZone::parse(rule.read_string()?).ok_or(ParseError::ValueError)?)
+            }
+            Err(JSONParsingError::KeyNotFound) => None,
+            Err(err) => Err(err)?,
+        };
+
+        let time = match (json.get_key_value("time"), json.get_key_value("epoch")) {
+            (Ok(time), _) => Some(time.read_string()?.parse()?),
+            (_, Ok(epoch)) => Some(Self::parse_epoch(&json, epoch.read_integer()?)?),
+            (Err(JSONParsingError::KeyNotFound), Err(JSONParsingError::KeyNotFound))
+                if time_zone.is_some() =>
+            {
+                None
+            }
+            (Err(err), _) => Err(err)?,
+        };
+
+        Ok(Self { time, time_zone })
+    }
+
+    fn parse_epoch(json: &JSONValue, epoch: isize) -> Result<DateTime<FixedOffset>, ParseError> {
+        let offset = match json.get_key_value("offset") {
+            Ok(offset) => offset.read_integer()?,
+            Err(JSONParsingError::KeyNotFound) => 0,
+            Err(err) => Err(err)?,
+        };
+        let offset = i32::try_from(offset)
+            .ok()
+            .and_then(FixedOffset::east_opt)
+            .ok_or(ParseError::ValueError)?;
+
+        offset
+            .timestamp_opt(epoch as i64, 0)
+            .single()
+            .ok_or(ParseError::ValueError)
+    }
+}
+
+/// Response to `GET /time`, in the formats accepted by [`TimeRequest::parse_json`],
+/// `{"time": null}` while the clock of the lamp is not set.
+pub fn time_to_json(
+    time: Option<DateTime<FixedOffset>>,
+    time_zone: Option<&LocalTimeZone>,
+) -> String {
+    let mut json = String::new();
+    // writing to a String does not fail
+    let _ = match time {
+        Some(time) => write!(
+            json,
+            "{{\"time\": \"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}\", \"epoch\": {}, \"offset\": {}",
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second(),
+            time.offset(),
+            time.timestamp(),
+            time.offset().local_minus_utc(),
+        ),
+        None => write!(json, "{{\"time\": null"),
+    };
+    if let Some(LocalTimeZone(rule)) = time_zone {
+        let _ = write!(json, ", \"time_zone\": \"{rule}\"");
+    }
+    json.push('}');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(rfc3339: &str) -> DateTime<FixedOffset> {
+        rfc3339.parse().unwrap()
+    }
+
+    fn parse_time(body: &str) -> Result<DateTime<FixedOffset>, ParseError> {
+        TimeRequest::parse_json(body).map(|request| request.time.unwrap())
+    }
+
+    #[test]
+    fn parses_both_formats() {
+        assert_eq!(
+            parse_time(r#"{"time": "2025-06-01T08:00:00+02:00"}"#).unwrap(),
+            time("2025-06-01T08:00:00+02:00")
+        );
+
+        let parsed = parse_time(r#"{"epoch": 1748757600, "offset": 7200}"#).unwrap();
+        assert_eq!(parsed, time("2025-06-01T08:00:00+02:00"));
+        assert_eq!(parsed.offset().local_minus_utc(), 7200);
+
+        let parsed = parse_time(r#"{"epoch": 1748757600}"#).unwrap();
+        assert_eq!(parsed, time("2025-06-01T06:00:00+00:00"));
+        assert_eq!(parsed.offset().local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn parses_time_zones() {
+        let request =
+            TimeRequest::parse_json(r#"{"time_zone": "CET-1CEST,M3.5.0,M10.5.0/3"}"#).unwrap();
+        assert_eq!(request.time, None);
+        assert!(request.time_zone.unwrap().rule().is_some());
+
+        let request = TimeRequest::parse_json(r#"{"epoch": 1748757600, "time_zone": ""}"#).unwrap();
+        assert!(request.time.is_some());
+        assert_eq!(request.time_zone.unwrap().rule(), None);
+    }
+
+    #[test]
+    fn rejects_bad_times() {
+        assert!(matches!(
+            parse_time(r#"{"time": "noon"}"#),
+            Err(ParseError::ChronoError(_))
+        ));
+        assert!(matches!(
+            parse_time(r#"{"epoch": 1748757600, "offset": 90000}"#),
+            Err(ParseError::ValueError)
+        ));
+        assert!(matches!(
+            parse_time(r#"{"offset": 7200}"#),
+            Err(ParseError::JsonError(_))
+        ));
+        assert!(matches!(
+            TimeRequest::parse_json(r#"{"time_zone": "CET-1CEST,M3.5.0"}"#),
+            Err(ParseError::ValueError)
+        ));
+    }
+
+    #[test]
+    fn writes_time() {
+        assert_eq!(time_to_json(None, None), r#"{"time": null}"#);
+
+        let json = time_to_json(Some(time("2025-06-01T08:00:09-05:30")), None);
+        assert_eq!(
+            json,
+            r#"{"time": "2025-06-01T08:00:09-05:30", "epoch": 1748784609, "offset": -19800}"#
+        );
+        assert_eq!(
+            parse_time(&json).unwrap(),
+            time("2025-06-01T08:00:09-05:30")
+        );
+
+        let zone = LocalTimeZone::parse("EST5EDT").unwrap();
+        assert_eq!(
+            time_to_json(None, Some(&zone)),
+            r#"{"time": null, "time_zone": "EST5EDT"}"#
+        );
+    }
+}
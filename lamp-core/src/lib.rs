@@ -0,0 +1,12 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod config;
+pub mod effects;
+pub mod http;
+pub mod output;
+pub mod runner;
+pub mod settings;
+pub mod sntp;
+pub mod types;
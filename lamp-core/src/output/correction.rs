@@ -0,0 +1,135 @@
+use crate::types::Color;
+
+/// Relation between the brightness an effect asks for and the PWM duty sent to the LEDs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BrightnessCurve {
+    /// Values are sent to the LEDs unchanged.
+    Linear,
+    /// Power curve with the given exponent, most LEDs look even around 2.2 - 2.8.
+    Gamma(f32),
+    /// CIE 1931 lightness, perceptually even down to the darkest levels.
+    #[default]
+    Cie,
+}
+
+impl BrightnessCurve {
+    /// Maps perceived brightness to luminance, both in 0..=1.
+    fn luminance(&self, brightness: f32) -> f32 {
+        match *self {
+            BrightnessCurve::Linear => brightness,
+            BrightnessCurve::Gamma(gamma) => libm::powf(brightness, gamma),
+            BrightnessCurve::Cie => {
+                let lightness = brightness * 100.0;
+                if lightness <= 8.0 {
+                    lightness / 903.3
+                } else {
+                    libm::powf((lightness + 16.0) / 116.0, 3.0)
+                }
+            }
+        }
+    }
+}
+
+// Distance between the 16-bit inputs the lookup table is sampled at
+const LUT_STEP: u32 = 257;
+
+/// Output stage between effects and the LED controller.
+#[derive(Clone)]
+pub struct ColorCorrection {
+    // corrected values of the 8-bit levels, in between them the curve is interpolated
+    lut: [u16; 256],
+}
+
+impl ColorCorrection {
+    pub fn new(curve: BrightnessCurve) -> Self {
+        let mut lut = [0; 256];
+        for (value, out) in lut.iter_mut().enumerate() {
+            let luminance = curve.luminance(value as f32 / 255.0).clamp(0.0, 1.0);
+            *out = libm::roundf(luminance * u16::MAX as f32) as u16;
+        }
+        Self { lut }
+    }
+
+    fn correct(&self, value: u16) -> u16 {
+        let idx = value as usize / LUT_STEP as usize;
+        let frac = value as u32 % LUT_STEP;
+        if frac == 0 {
+            return self.lut[idx];
+        }
+
+        let low = self.lut[idx] as u32;
+        let high = self.lut[idx + 1] as u32;
+        (low + (high - low) * frac / LUT_STEP) as u16
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        let [r, g, b] = color.rgb16().map(|c| self.correct(c));
+        Color::new16(r, g, b)
+    }
+
+    /// Writes the corrected colors of `frame` into `output`.
+    pub fn apply_frame(&self, frame: &[Color], output: &mut [Color]) {
+        output
+            .iter_mut()
+            .zip(frame)
+            .for_each(|(out, &color)| *out = self.apply(color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curves() -> [BrightnessCurve; 3] {
+        [
+            BrightnessCurve::Linear,
+            BrightnessCurve::Gamma(2.2),
+            BrightnessCurve::Cie,
+        ]
+    }
+
+    #[test]
+    fn keeps_black_and_full_brightness() {
+        for curve in curves() {
+            let correction = ColorCorrection::new(curve);
+            assert_eq!(correction.correct(0), 0, "{curve:?}");
+            assert_eq!(correction.correct(u16::MAX), u16::MAX, "{curve:?}");
+        }
+    }
+
+    #[test]
+    fn is_monotonic() {
+        for curve in curves() {
+            let correction = ColorCorrection::new(curve);
+            assert!(correction.lut.is_sorted(), "{curve:?}");
+            assert!(
+                (0..=u16::MAX).map(|v| correction.correct(v)).is_sorted(),
+                "{curve:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn darkens_midtones() {
+        let linear = ColorCorrection::new(BrightnessCurve::Linear);
+        let gamma = ColorCorrection::new(BrightnessCurve::Gamma(2.2));
+        let cie = ColorCorrection::new(BrightnessCurve::Cie);
+
+        assert_eq!(linear.apply(Color::new(128, 64, 0)), Color::new(128, 64, 0));
+        assert_eq!(
+            linear.apply(Color::new16(1000, 1, 0)),
+            Color::new16(1000, 1, 0)
+        );
+        assert_eq!(gamma.apply(Color::new(128, 64, 0)).rgb(), [56, 12, 0]);
+        assert_eq!(cie.apply(Color::new(128, 64, 0)).rgb(), [47, 11, 0]);
+    }
+
+    #[test]
+    fn keeps_precision_at_the_low_end() {
+        let cie = ColorCorrection::new(BrightnessCurve::Cie);
+
+        // the first 8-bit level stays well below the first 8-bit output step
+        assert_eq!(cie.apply(Color::new(1, 0, 0)).rgb16(), [28, 0, 0]);
+        assert_eq!(cie.apply(Color::new16(128, 0, 0)).rgb16(), [13, 0, 0]);
+    }
+}
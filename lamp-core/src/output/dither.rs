@@ -0,0 +1,161 @@
+use super::{Pixel, StripLayout};
+use crate::types::Color;
+
+const MAX16: u32 = u16::MAX as u32;
+
+/// Temporal dithering of one LED from 16-bit colors down to 8-bit pixels.
+///
+/// The rounding error of every frame is carried over to the next one,
+/// so over a few frames the LED averages out to the exact 16-bit color.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Dither {
+    // rounding error in units of 1/65535 of an 8-bit step
+    residue: [u32; 4],
+}
+
+impl Dither {
+    /// Returns the pixel to show in this frame and whether the next frames differ from it.
+    pub fn next(&mut self, color: Color) -> (Pixel, bool) {
+        let [r, g, b] = color.rgb16();
+        self.next_channels([r, g, b, 0])
+    }
+
+    /// Same as [`Dither::next`] for red, green, blue and white intensities.
+    pub fn next_channels(&mut self, values: [u16; 4]) -> (Pixel, bool) {
+        let mut fractional = false;
+        let mut channels = [0; 4];
+        for ((out, value), residue) in channels.iter_mut().zip(values).zip(&mut self.residue) {
+            let scaled = value as u32 * 255;
+            fractional |= !scaled.is_multiple_of(MAX16);
+
+            let scaled = scaled + *residue;
+            *out = (scaled / MAX16) as u8;
+            *residue = scaled % MAX16;
+        }
+
+        let [r, g, b, w] = channels;
+        (Pixel::new_rgbw(r, g, b, w), fractional)
+    }
+
+    /// Pixel closest to `values`, for colors that stand still and are not refreshed.
+    /// The rounding error carried so far is dropped.
+    pub fn settle_channels(&mut self, values: [u16; 4]) -> Pixel {
+        self.residue = [0; 4];
+        let [r, g, b, w] = values.map(|value| ((value as u32 * 255 + MAX16 / 2) / MAX16) as u8);
+        Pixel::new_rgbw(r, g, b, w)
+    }
+}
+
+/// Dithers `frame` into `output` for a strip with the given `layout` and returns
+/// whether any of the LEDs needs further frames to show its exact color.
+pub fn dither_frame(
+    frame: &[Color],
+    layout: StripLayout,
+    dithers: &mut [Dither],
+    output: &mut [Pixel],
+) -> bool {
+    let mut fractional = false;
+    for ((color, dither), out) in frame.iter().zip(dithers).zip(output) {
+        let (pixel, frac) = dither.next_channels(layout.split(*color));
+        *out = pixel;
+        fractional |= frac;
+    }
+    fractional
+}
+
+/// Rounds `frame` into `output` like [`dither_frame`] without carrying errors to later frames.
+pub fn settle_frame(
+    frame: &[Color],
+    layout: StripLayout,
+    dithers: &mut [Dither],
+    output: &mut [Pixel],
+) {
+    for ((color, dither), out) in frame.iter().zip(dithers).zip(output) {
+        *out = dither.settle_channels(layout.split(*color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_8_bit_colors_through() {
+        let mut dither = Dither::default();
+        for _ in 0..10 {
+            assert_eq!(
+                dither.next(Color::new(0, 17, 255)),
+                (Pixel::new(0, 17, 255), false)
+            );
+        }
+    }
+
+    #[test]
+    fn averages_to_the_16_bit_color() {
+        let mut dither = Dither::default();
+        // a quarter of the way between 8-bit levels 10 and 11
+        let color = Color::new16(10 * 257 + 64, 0, 0);
+
+        let frames: Vec<_> = (0..257).map(|_| dither.next(color)).collect();
+        assert!(frames.iter().all(|(_, fractional)| *fractional));
+
+        let sum: u32 = frames.iter().map(|(pixel, _)| pixel.r as u32).sum();
+        assert_eq!(sum, 257 * 10 + 64);
+        assert!(
+            frames
+                .iter()
+                .all(|(pixel, _)| pixel.r == 10 || pixel.r == 11)
+        );
+    }
+
+    #[test]
+    fn dithers_the_white_channel() {
+        let frame = [Color::new16(1000, 1000, 1000); 2];
+        let mut dithers = [Dither::default(); 2];
+        let mut output = [Pixel::default(); 2];
+
+        let mut white = 0;
+        for _ in 0..257 {
+            assert!(dither_frame(
+                &frame,
+                StripLayout::SK6812_RGBW,
+                &mut dithers,
+                &mut output
+            ));
+            assert_eq!((output[0].r, output[0].g, output[0].b), (0, 0, 0));
+            white += output[1].w as u32;
+        }
+        assert_eq!(white, 1000);
+    }
+
+    #[test]
+    fn settles_to_the_closest_step() {
+        let mut dither = Dither::default();
+        dither.next(Color::new16(10 * 257 + 200, 0, 0));
+
+        assert_eq!(
+            dither.settle_channels([10 * 257 + 100, 0, 0, 0]),
+            Pixel::new(10, 0, 0)
+        );
+        assert_eq!(
+            dither.settle_channels([10 * 257 + 200, 0, 0, 0]),
+            Pixel::new(11, 0, 0)
+        );
+        // nothing is left over for the next frames
+        assert_eq!(
+            dither.next(Color::new(3, 0, 0)),
+            (Pixel::new(3, 0, 0), false)
+        );
+    }
+
+    #[test]
+    fn lights_up_below_the_first_step() {
+        let mut dither = Dither::default();
+        let color = Color::new16(30, 0, 0);
+
+        let lit = (0..1000)
+            .filter(|_| dither.next(color).0 == Pixel::new(1, 0, 0))
+            .count();
+        assert_eq!(lit, 116);
+    }
+}
@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+use super::{Dither, Pixel, StripLayout};
+use crate::types::Color;
+
+/// Hardware that shows frames of pixels on an LED strip.
+#[allow(async_fn_in_trait)]
+pub trait LedDriver {
+    type Error;
+
+    fn num_leds(&self) -> usize;
+
+    /// Wire format of the strip, the pixels are prepared for it before they are sent.
+    fn layout(&self) -> StripLayout;
+
+    /// Shows `frame[i]` on the i-th LED of the strip.
+    /// Pixels beyond the length of the strip are ignored.
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), Self::Error>;
+
+    /// Whether the strip resolves more than the 8 bits of a pixel per channel.
+    /// The runner sends it the corrected colors with [`LedDriver::send_colors`] then,
+    /// instead of dithering them down to pixels.
+    fn takes_colors(&self) -> bool {
+        false
+    }
+
+    /// Shows `frame[i]`, already corrected to the brightness curve, on the i-th LED of the strip.
+    /// By default the colors are rounded to pixels for [`LedDriver::send_frame`].
+    async fn send_colors(&mut self, frame: &[Color]) -> Result<(), Self::Error> {
+        let layout = self.layout();
+        let pixels: Vec<Pixel> = frame
+            .iter()
+            .map(|color| Dither::default().settle_channels(layout.split(*color)))
+            .collect();
+        self.send_frame(&pixels).await
+    }
+}
+
+/// Driver that records the frames instead of showing them, so the LED loop can run on a PC.
+pub struct MockDriver {
+    num_leds: usize,
+    layout: StripLayout,
+    pub frames: Vec<Vec<Pixel>>,
+}
+
+impl MockDriver {
+    pub fn new(num_leds: usize, layout: StripLayout) -> Self {
+        Self {
+            num_leds,
+            layout,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn last_frame(&self) -> Option<&[Pixel]> {
+        self.frames.last().map(Vec::as_slice)
+    }
+}
+
+impl LedDriver for MockDriver {
+    type Error = Infallible;
+
+    fn num_leds(&self) -> usize {
+        self.num_leds
+    }
+
+    fn layout(&self) -> StripLayout {
+        self.layout
+    }
+
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), Self::Error> {
+        let frame = frame.iter().copied().take(self.num_leds).collect();
+        self.frames.push(frame);
+        Ok(())
+    }
+}
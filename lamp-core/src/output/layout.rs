@@ -0,0 +1,134 @@
+use super::Pixel;
+use crate::types::Color;
+
+/// Order in which a strip expects the color channels of every LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelOrder {
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ChannelOrder {
+    fn arrange(self, [r, g, b]: [u8; 3]) -> [u8; 3] {
+        match self {
+            ChannelOrder::Rgb => [r, g, b],
+            ChannelOrder::Rbg => [r, b, g],
+            ChannelOrder::Grb => [g, r, b],
+            ChannelOrder::Gbr => [g, b, r],
+            ChannelOrder::Brg => [b, r, g],
+            ChannelOrder::Bgr => [b, g, r],
+        }
+    }
+}
+
+/// Wire format of an LED strip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StripLayout {
+    pub order: ChannelOrder,
+    /// The strip has a white LED in every pixel, sent after the color channels.
+    pub white: bool,
+}
+
+impl StripLayout {
+    /// WS2812, WS2813 and most other RGB strips.
+    pub const WS2812: Self = Self {
+        order: ChannelOrder::Grb,
+        white: false,
+    };
+
+    /// SK6812 RGBW strips.
+    pub const SK6812_RGBW: Self = Self {
+        order: ChannelOrder::Grb,
+        white: true,
+    };
+
+    /// APA102 and SK9822 strips with a clock line.
+    pub const APA102: Self = Self {
+        order: ChannelOrder::Bgr,
+        white: false,
+    };
+
+    /// Number of bytes sent for every LED.
+    pub fn channels(&self) -> usize {
+        if self.white { 4 } else { 3 }
+    }
+
+    /// Splits `color` into red, green, blue and white intensities.
+    ///
+    /// On strips with a white channel the part shared by all three colors is shown
+    /// by the white LED instead, which gives a cleaner and more efficient white.
+    pub fn split(&self, color: Color) -> [u16; 4] {
+        let [r, g, b] = color.rgb16();
+        if !self.white {
+            return [r, g, b, 0];
+        }
+
+        let w = r.min(g).min(b);
+        [r - w, g - w, b - w, w]
+    }
+
+    /// Bytes of `pixel` in the order they are sent to the strip.
+    pub fn encode(&self, pixel: Pixel) -> impl Iterator<Item = u8> {
+        let [a, b, c] = self.order.arrange([pixel.r, pixel.g, pixel.b]);
+        [a, b, c, pixel.w].into_iter().take(self.channels())
+    }
+}
+
+impl Default for StripLayout {
+    fn default() -> Self {
+        Self::WS2812
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(layout: StripLayout, pixel: Pixel) -> Vec<u8> {
+        layout.encode(pixel).collect()
+    }
+
+    #[test]
+    fn orders_channels() {
+        let pixel = Pixel::new(1, 2, 3);
+        assert_eq!(encoded(StripLayout::WS2812, pixel), [2, 1, 3]);
+
+        let layout = |order| StripLayout {
+            order,
+            white: false,
+        };
+        assert_eq!(encoded(layout(ChannelOrder::Rgb), pixel), [1, 2, 3]);
+        assert_eq!(encoded(layout(ChannelOrder::Brg), pixel), [3, 1, 2]);
+        assert_eq!(encoded(layout(ChannelOrder::Bgr), pixel), [3, 2, 1]);
+    }
+
+    #[test]
+    fn sends_white_last() {
+        assert_eq!(
+            encoded(StripLayout::SK6812_RGBW, Pixel::new_rgbw(1, 2, 3, 4)),
+            [2, 1, 3, 4]
+        );
+        assert_eq!(
+            encoded(StripLayout::WS2812, Pixel::new_rgbw(1, 2, 3, 4)).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn extracts_white() {
+        let warm = Color::new16(65535, 62708, 51400);
+        assert_eq!(StripLayout::WS2812.split(warm), [65535, 62708, 51400, 0]);
+        assert_eq!(
+            StripLayout::SK6812_RGBW.split(warm),
+            [14135, 11308, 0, 51400]
+        );
+        assert_eq!(
+            StripLayout::SK6812_RGBW.split(Color::new16(0, 100, 200)),
+            [0, 100, 200, 0]
+        );
+    }
+}
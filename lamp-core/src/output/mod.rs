@@ -0,0 +1,14 @@
+mod correction;
+pub use correction::{BrightnessCurve, ColorCorrection};
+
+mod dither;
+pub use dither::{Dither, dither_frame, settle_frame};
+
+mod driver;
+pub use driver::{LedDriver, MockDriver};
+
+mod layout;
+pub use layout::{ChannelOrder, StripLayout};
+
+mod pixel;
+pub use pixel::Pixel;
@@ -0,0 +1,18 @@
+/// 8-bit color as it is sent to the LEDs, `w` is only used by strips with a white channel.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Pixel {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub w: u8,
+}
+
+impl Pixel {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self::new_rgbw(r, g, b, 0)
+    }
+
+    pub const fn new_rgbw(r: u8, g: u8, b: u8, w: u8) -> Self {
+        Self { r, g, b, w }
+    }
+}
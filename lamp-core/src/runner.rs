@@ -0,0 +1,497 @@
+use alloc::{string::String, vec, vec::Vec};
+use core::ops::Range;
+
+use embassy_time::Duration;
+
+use crate::{
+    effects::{EffectEnum, EffectStatus},
+    http::LedRequest,
+    output::{ColorCorrection, Dither, LedDriver, Pixel, dither_frame, settle_frame},
+    types::{Color, clock::Clock},
+};
+
+// Shortest time between two frames, also the refresh period while dithering
+pub const FRAME_TIME: Duration = Duration::from_millis(5);
+// Colors are dithered while the effects change them again within this time,
+// colors that stand still longer are rounded and not refreshed
+const DITHER_HOLD: Duration = Duration::from_secs(1);
+
+/// Named range of LEDs of a strip that runs its own effect.
+/// Where segments overlap, the effect of the later one in the list is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub leds: Range<usize>,
+}
+
+struct RunningSegment {
+    segment: Segment,
+    // requested LEDs before cutting to the strip length
+    configured: Range<usize>,
+    // the LEDs stay dark until the first request
+    effect: Option<EffectEnum>,
+}
+
+/// Renders the running effects through the output stage onto an LED driver.
+///
+/// Waiting for the next frame or request is left to the caller,
+/// so the same loop runs on the lamp and in tests.
+pub struct LedRunner<D> {
+    driver: D,
+    correction: ColorCorrection,
+    segments: Vec<RunningSegment>,
+    frame: Vec<Color>,
+    corrected: Vec<Color>,
+    dithers: Vec<Dither>,
+    pixels: Vec<Pixel>,
+}
+
+impl<D: LedDriver> LedRunner<D> {
+    /// Without `segments` the whole strip is a single segment.
+    /// Segments are cut to the length of the strip, inverted ranges like `8..2` are empty.
+    pub fn new(driver: D, correction: ColorCorrection, segments: Vec<Segment>) -> Self {
+        let num_leds = driver.num_leds();
+        let segments = match segments.is_empty() {
+            true => vec![Segment {
+                name: String::from("strip"),
+                leds: 0..usize::MAX,
+            }],
+            false => segments,
+        };
+        let segments = segments
+            .into_iter()
+            .map(|Segment { name, leds }| RunningSegment {
+                segment: Segment {
+                    name,
+                    leds: cut(&leds, num_leds),
+                },
+                configured: leds,
+                effect: None,
+            })
+            .collect();
+
+        Self {
+            driver,
+            correction,
+            segments,
+            frame: vec![Color::black(); num_leds],
+            corrected: vec![Color::black(); num_leds],
+            dithers: vec![Dither::default(); num_leds],
+            pixels: vec![Pixel::default(); num_leds],
+        }
+    }
+
+    /// Changes the driver through `reconfigure`, e.g. to a new strip length.
+    /// The effects keep running on the segments cut to the length of the driver afterwards.
+    pub fn reconfigure<R>(&mut self, reconfigure: impl FnOnce(&mut D) -> R) -> R {
+        let result = reconfigure(&mut self.driver);
+
+        let num_leds = self.driver.num_leds();
+        for running in &mut self.segments {
+            running.segment.leds = cut(&running.configured, num_leds);
+        }
+        self.frame.resize(num_leds, Color::black());
+        self.corrected.resize(num_leds, Color::black());
+        self.dithers.resize(num_leds, Dither::default());
+        self.pixels.resize(num_leds, Pixel::default());
+
+        result
+    }
+
+    /// Sends the following frames through another brightness curve.
+    pub fn set_correction(&mut self, correction: ColorCorrection) {
+        self.correction = correction;
+    }
+
+    pub fn into_driver(self) -> D {
+        self.driver
+    }
+
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Colors of the last frame before correction.
+    pub fn frame(&self) -> &[Color] {
+        &self.frame
+    }
+
+    pub fn has_segment(&self, name: &str) -> bool {
+        self.segments
+            .iter()
+            .any(|running| running.segment.name == name)
+    }
+
+    /// Replaces the effect of the named segment, or of all segments when `segment` is `None`,
+    /// with the one of `request`. Returns whether any segment matched.
+    pub fn apply(
+        &mut self,
+        request: LedRequest,
+        segment: Option<&str>,
+        clock: &impl Clock,
+    ) -> bool {
+        let mut matched = false;
+        for running in &mut self.segments {
+            if segment.is_some_and(|name| name != running.segment.name) {
+                continue;
+            }
+
+            // effects blend from a single color, the one of the first LED of the segment
+            let current_color = self
+                .frame
+                .get(running.segment.leds.start)
+                .copied()
+                .unwrap_or_default();
+            running.effect = Some(EffectEnum::from_request(
+                request.clone(),
+                current_color,
+                clock,
+            ));
+            matched = true;
+        }
+        matched
+    }
+
+    /// Moves the effects that follow the time of day to the wall time of `clock`
+    /// after it was set, e.g. when the clock of the lamp was synchronized.
+    pub fn time_changed(&mut self, clock: &impl Clock) {
+        for effect in self
+            .segments
+            .iter_mut()
+            .filter_map(|running| running.effect.as_mut())
+        {
+            effect.time_changed(clock);
+        }
+    }
+
+    /// Sends the current frame of the effects to the driver.
+    ///
+    /// Returns how long to wait before the next update,
+    /// `None` if nothing changes until the next request.
+    pub async fn update(&mut self, clock: &impl Clock) -> Option<Duration> {
+        let mut next_update: Option<Duration> = None;
+        for running in &mut self.segments {
+            let Some(effect) = &mut running.effect else {
+                continue;
+            };
+            let leds = running.segment.leds.clone();
+            if let EffectStatus::InProgress(timeout) = effect.step(clock, &mut self.frame[leds]) {
+                next_update = Some(next_update.map_or(timeout, |next| next.min(timeout)));
+            }
+        }
+
+        self.correction
+            .apply_frame(&self.frame, &mut self.corrected);
+        // finer outputs show the corrected colors as they are, without dithering
+        if self.driver.takes_colors() {
+            let _ = self.driver.send_colors(&self.corrected).await;
+            return next_update.map(|timeout| timeout.max(FRAME_TIME));
+        }
+
+        let changing = next_update.is_some_and(|timeout| timeout <= DITHER_HOLD);
+        let layout = self.driver.layout();
+        let dithering = match changing {
+            true => dither_frame(&self.corrected, layout, &mut self.dithers, &mut self.pixels),
+            false => {
+                settle_frame(&self.corrected, layout, &mut self.dithers, &mut self.pixels);
+                false
+            }
+        };
+        let _ = self.driver.send_frame(&self.pixels).await;
+
+        // dithered colors need fresh frames between the changes of the effects
+        match next_update {
+            _ if dithering => Some(FRAME_TIME),
+            Some(timeout) => Some(timeout.max(FRAME_TIME)),
+            None => None,
+        }
+    }
+}
+
+fn cut(leds: &Range<usize>, num_leds: usize) -> Range<usize> {
+    let end = leds.end.min(num_leds);
+    leds.start.min(end)..end
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::{
+        effects::Transition,
+        output::{BrightnessCurve, MockDriver, StripLayout},
+        types::clock::ManualClock,
+    };
+
+    fn runner(num_leds: usize, segments: &[(&str, Range<usize>)]) -> LedRunner<MockDriver> {
+        let segments = segments
+            .iter()
+            .map(|(name, leds)| Segment {
+                name: String::from(*name),
+                leds: leds.clone(),
+            })
+            .collect();
+        LedRunner::new(
+            MockDriver::new(num_leds, StripLayout::WS2812),
+            ColorCorrection::new(BrightnessCurve::Linear),
+            segments,
+        )
+    }
+
+    fn set(color: Color, millis: u64) -> LedRequest {
+        LedRequest::Set(color, Duration::from_millis(millis), Transition::default())
+    }
+
+    #[test]
+    fn shows_effects_and_requests() {
+        let clock = ManualClock::default();
+        let mut runner = runner(3, &[]);
+
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert!(runner.apply(set(Color::new(255, 0, 0), 1000), None, &clock));
+
+        assert_eq!(block_on(runner.update(&clock)), Some(FRAME_TIME));
+        assert_eq!(
+            runner.driver().last_frame(),
+            Some(&[Pixel::default(); 3][..])
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(
+            runner.driver().last_frame(),
+            Some(&[Pixel::new(255, 0, 0); 3][..])
+        );
+
+        let request =
+            LedRequest::parse_json(r#"{"type": "set", "color": [0, 0, 255], "duration": 10000}"#)
+                .unwrap();
+        runner.apply(request, None, &clock);
+        assert!(block_on(runner.update(&clock)).is_some());
+        assert_eq!(runner.frame()[0], Color::new(255, 0, 0));
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(
+            runner.driver().last_frame(),
+            Some(&[Pixel::new(0, 0, 255); 3][..])
+        );
+        assert_eq!(runner.driver().frames.len(), 5);
+    }
+
+    #[test]
+    fn refreshes_while_dithering() {
+        let clock = ManualClock::default();
+        let mut runner = runner(3, &[]);
+        // a slow fade from the first 8-bit level to the second one
+        runner.apply(set(Color::new16(514, 0, 0), 100_000), None, &clock);
+        clock.advance(Duration::from_secs(60));
+
+        for _ in 0..100 {
+            assert_eq!(block_on(runner.update(&clock)), Some(FRAME_TIME));
+            clock.advance(FRAME_TIME);
+        }
+        let lit = runner
+            .driver()
+            .frames
+            .iter()
+            .filter(|frame| frame[0].r == 2)
+            .count();
+        assert_eq!(lit, 20);
+
+        // the end of the fade stands still
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(runner.driver().last_frame().unwrap()[0].r, 2);
+    }
+
+    #[test]
+    fn goes_idle_on_static_colors() {
+        let clock = ManualClock::default();
+        let mut runner = runner(1, &[]);
+
+        // exactly on an 8-bit level and between two of them
+        for (color, pixel) in [
+            (Color::new(10, 0, 0), Pixel::new(10, 0, 0)),
+            (Color::new16(10 * 257 + 200, 0, 0), Pixel::new(11, 0, 0)),
+        ] {
+            runner.apply(set(color, 0), None, &clock);
+            assert_eq!(block_on(runner.update(&clock)), None);
+            assert_eq!(runner.driver().last_frame(), Some(&[pixel][..]));
+        }
+    }
+
+    // records the colors of an output finer than 8 bits
+    #[derive(Default)]
+    struct ColorDriver {
+        frames: Vec<Vec<Color>>,
+    }
+
+    impl LedDriver for ColorDriver {
+        type Error = core::convert::Infallible;
+
+        fn num_leds(&self) -> usize {
+            1
+        }
+
+        fn layout(&self) -> StripLayout {
+            StripLayout::WS2812
+        }
+
+        async fn send_frame(&mut self, _: &[Pixel]) -> Result<(), Self::Error> {
+            panic!("the colors were dithered")
+        }
+
+        fn takes_colors(&self) -> bool {
+            true
+        }
+
+        async fn send_colors(&mut self, frame: &[Color]) -> Result<(), Self::Error> {
+            self.frames.push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sends_colors_to_finer_drivers() {
+        let clock = ManualClock::default();
+        let correction = ColorCorrection::new(BrightnessCurve::Linear);
+        let mut runner = LedRunner::new(ColorDriver::default(), correction, Vec::new());
+        // a slow fade between the first two 8-bit levels
+        runner.apply(set(Color::new16(514, 0, 0), 100_000), None, &clock);
+        clock.advance(Duration::from_secs(60));
+
+        assert_ne!(block_on(runner.update(&clock)), Some(FRAME_TIME));
+        let color = runner.driver().frames.last().unwrap()[0];
+        assert_eq!(color, runner.frame()[0]);
+        assert!(color.rgb16()[0] > 257 && color.rgb16()[0] < 514);
+
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(
+            runner.driver().frames.last().unwrap()[0],
+            Color::new16(514, 0, 0)
+        );
+    }
+
+    #[test]
+    fn runs_segments_independently() {
+        let clock = ManualClock::default();
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        let mut runner = runner(4, &[("top", 0..2), ("back", 2..10)]);
+
+        assert!(runner.has_segment("back"));
+        assert!(!runner.apply(set(red, 0), Some("middle"), &clock));
+        assert!(runner.apply(set(red, 1000), Some("top"), &clock));
+        assert!(runner.apply(set(blue, 4000), Some("back"), &clock));
+
+        clock.advance(Duration::from_secs(2));
+        assert!(block_on(runner.update(&clock)).is_some());
+        assert_eq!(&runner.frame()[..2], [red; 2]);
+        assert_eq!(runner.frame()[2], Color::black().interpolate(blue, 1, 2));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(runner.frame(), [red, red, blue, blue]);
+
+        // without a segment the request goes to all of them
+        runner.apply(set(blue, 0), None, &clock);
+        block_on(runner.update(&clock));
+        assert_eq!(runner.frame(), [blue; 4]);
+    }
+
+    #[test]
+    fn leaves_inverted_segments_dark() {
+        let clock = ManualClock::default();
+        let red = Color::new(255, 0, 0);
+        let inverted = Range { start: 3, end: 1 };
+        let mut runner = runner(4, &[("top", 0..2), ("back", inverted)]);
+
+        assert!(runner.apply(set(red, 0), Some("back"), &clock));
+        assert!(runner.apply(set(red, 0), Some("top"), &clock));
+        assert_eq!(block_on(runner.update(&clock)), None);
+        assert_eq!(runner.frame(), [red, red, Color::black(), Color::black()]);
+    }
+
+    #[test]
+    fn shows_the_later_of_overlapping_segments() {
+        let clock = ManualClock::default();
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        let mut runner = runner(4, &[("top", 0..3), ("back", 2..4)]);
+
+        runner.apply(set(blue, 0), Some("back"), &clock);
+        runner.apply(set(red, 0), Some("top"), &clock);
+        block_on(runner.update(&clock));
+        assert_eq!(runner.frame(), [red, red, blue, blue]);
+    }
+
+    #[test]
+    fn waits_for_the_earliest_segment() {
+        let clock = ManualClock::default();
+        let mut runner = runner(2, &[("left", 0..1), ("right", 1..2)]);
+        clock.set_wall_time("2014-11-28T06:00:00+00:00".parse().unwrap());
+        let cycle = |minutes: &str| {
+            let body = alloc::format!(
+                r#"{{"type": "cycle", "on_color": [255, 244, 200], "cycle_minutes": {minutes}}}"#
+            );
+            LedRequest::parse_json(&body).unwrap()
+        };
+
+        runner.apply(cycle("[540, 600, 1260, 1320]"), Some("left"), &clock);
+        runner.apply(cycle("[480, 600, 1260, 1320]"), Some("right"), &clock);
+
+        assert_eq!(
+            block_on(runner.update(&clock)),
+            Some(Duration::from_secs(2 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn moves_cycles_to_a_new_time() {
+        let clock = ManualClock::default();
+        let mut runner = runner(2, &[("cycle", 0..1), ("set", 1..2)]);
+        clock.set_wall_time("2014-11-28T06:00:00+00:00".parse().unwrap());
+        let cycle = LedRequest::parse_json(
+            r#"{"type": "cycle", "on_color": [255, 244, 200], "cycle_minutes": [540, 600, 1260, 1320]}"#,
+        )
+        .unwrap();
+        runner.apply(cycle, Some("cycle"), &clock);
+        runner.apply(set(Color::new(255, 0, 0), 0), Some("set"), &clock);
+        block_on(runner.update(&clock));
+
+        // the clock was three hours behind, it is daytime already
+        clock.set_wall_time("2014-11-28T12:00:00+00:00".parse().unwrap());
+        runner.time_changed(&clock);
+
+        clock.advance(Duration::from_secs(60 * 60));
+        block_on(runner.update(&clock));
+        assert_eq!(
+            runner.frame(),
+            [Color::new(255, 244, 200), Color::new(255, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn keeps_effects_on_a_reconfigured_driver() {
+        let clock = ManualClock::default();
+        let red = Color::new(255, 0, 0);
+        let mut runner = runner(2, &[("top", 0..3)]);
+        runner.apply(set(red, 0), None, &clock);
+        block_on(runner.update(&clock));
+
+        let previous = runner.reconfigure(|driver| {
+            core::mem::replace(driver, MockDriver::new(5, StripLayout::WS2812))
+        });
+        assert_eq!(previous.num_leds(), 2);
+
+        block_on(runner.update(&clock));
+        assert_eq!(
+            runner.frame(),
+            [red, red, red, Color::black(), Color::black()]
+        );
+        assert_eq!(runner.driver().last_frame().unwrap().len(), 5);
+    }
+}
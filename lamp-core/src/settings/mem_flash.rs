@@ -0,0 +1,87 @@
+use alloc::{vec, vec::Vec};
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash, check_erase, check_read, check_write,
+};
+
+/// Flash in RAM that behaves like NOR flash: erasing sets all bits,
+/// writes can only clear them and every word is written once between erases.
+pub struct MemFlash {
+    data: Vec<u8>,
+    // the word was written since the last erase
+    written: Vec<bool>,
+    erase_counts: Vec<u32>,
+}
+
+impl MemFlash {
+    pub const SECTOR_SIZE: usize = 4096;
+
+    pub fn new(num_sectors: usize) -> Self {
+        let capacity = num_sectors * Self::SECTOR_SIZE;
+        Self {
+            data: vec![0xFF; capacity],
+            written: vec![false; capacity / Self::WRITE_SIZE],
+            erase_counts: vec![0; num_sectors],
+        }
+    }
+
+    /// How often each sector was erased.
+    pub fn erase_counts(&self) -> &[u32] {
+        &self.erase_counts
+    }
+
+    /// Raw contents, e.g. to simulate a write cut short by a power loss.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl ErrorType for MemFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for MemFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for MemFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = Self::SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        let (from, to) = (from as usize, to as usize);
+        self.data[from..to].fill(0xFF);
+        self.written[from / Self::WRITE_SIZE..to / Self::WRITE_SIZE].fill(false);
+        for count in &mut self.erase_counts[from / Self::ERASE_SIZE..to / Self::ERASE_SIZE] {
+            *count += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        let offset = offset as usize;
+        let words = offset / Self::WRITE_SIZE..(offset + bytes.len()) / Self::WRITE_SIZE;
+        if self.written[words.clone()].iter().any(|&written| written) {
+            Err(NorFlashErrorKind::Other)?
+        }
+
+        self.written[words].fill(true);
+        for (cell, byte) in self.data[offset..].iter_mut().zip(bytes) {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
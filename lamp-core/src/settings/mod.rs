@@ -0,0 +1,11 @@
+mod codec;
+pub use codec::{Decoder, Encoder};
+
+mod mem_flash;
+pub use mem_flash::MemFlash;
+
+mod records;
+pub use records::{LastRequest, LocalTimeZone, WifiCredentials};
+
+mod store;
+pub use store::{Setting, SettingsStore, StoreError};
@@ -0,0 +1,529 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+use embassy_time::Duration;
+use microjson::JSONValue;
+
+use super::{Decoder, Encoder, Setting};
+use crate::{
+    config::{DeviceConfig, PowerOn, StripConfig, Timings},
+    effects::{ColorSpace, Easing, Keyframe, Keyframes, MAX_KEYFRAMES, SunSchedule, Transition},
+    http::{LedRequest, ParseError, StripTarget, Target},
+    output::BrightnessCurve,
+    types::{Color, ranges::OverlapRanges, sun::Location, time_zone::TzRule},
+};
+
+/// Network the lamp joins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WifiCredentials {
+    pub ssid: String,
+    pub password: String,
+}
+
+impl WifiCredentials {
+    /*
+    expected format:
+    {
+        "ssid": "home",
+        "password": "secret"
+    }
+    */
+    pub fn parse_json(body: &str) -> Result<Self, ParseError> {
+        let json = JSONValue::load(body);
+        let ssid = json.get_key_value("ssid")?.read_string()?;
+        let password = json.get_key_value("password")?.read_string()?;
+
+        // limits of the WiFi standard
+        if ssid.is_empty() || ssid.len() > 32 || password.len() > 64 {
+            Err(ParseError::ValueError)?
+        }
+
+        Ok(Self {
+            ssid: ssid.to_string(),
+            password: password.to_string(),
+        })
+    }
+}
+
+/// POSIX TZ rule the local time of the lamp follows, empty to keep the offset it was set with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalTimeZone(pub String);
+
+impl LocalTimeZone {
+    /// Returns `None` if `rule` is neither empty nor a valid rule.
+    pub fn parse(rule: &str) -> Option<Self> {
+        if !rule.is_empty() {
+            TzRule::parse(rule)?;
+        }
+        Some(Self(rule.to_string()))
+    }
+
+    pub fn rule(&self) -> Option<TzRule> {
+        TzRule::parse(&self.0)
+    }
+}
+
+/// The last request applied to the strips, with the part of the lamp it targeted.
+#[derive(Clone)]
+pub struct LastRequest {
+    pub target: Target,
+    pub request: LedRequest,
+}
+
+// first byte of the power on policy
+const RESTORE: u8 = 0;
+const COLOR: u8 = 1;
+const OFF: u8 = 2;
+
+// first byte of the brightness curve
+const LINEAR: u8 = 0;
+const GAMMA: u8 = 1;
+const CIE: u8 = 2;
+
+impl Setting for DeviceConfig {
+    const KEY: u8 = 1;
+    const VERSION: u8 = 1;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u8(self.strips.len() as u8);
+        for strip in &self.strips {
+            let timings = &strip.timings;
+            encoder
+                .u16(strip.num_leds as u16)
+                .u8(strip.pin)
+                .u16(timings.t0h)
+                .u16(timings.t0l)
+                .u16(timings.t1h)
+                .u16(timings.t1l)
+                .u16(timings.reset);
+        }
+
+        match self.brightness_curve {
+            BrightnessCurve::Linear => {
+                encoder.u8(LINEAR);
+            }
+            BrightnessCurve::Gamma(gamma) => {
+                encoder.u8(GAMMA).u32(gamma.to_bits());
+            }
+            BrightnessCurve::Cie => {
+                encoder.u8(CIE);
+            }
+        }
+
+        match self.power_on {
+            PowerOn::Restore => {
+                encoder.u8(RESTORE);
+            }
+            PowerOn::Color(color) => encode_color(encoder.u8(COLOR), color),
+            PowerOn::Off => {
+                encoder.u8(OFF);
+            }
+        }
+    }
+
+    fn decode(_: u8, decoder: &mut Decoder) -> Option<Self> {
+        let num_strips = decoder.u8()?;
+        let strips = (0..num_strips)
+            .map(|_| {
+                Some(StripConfig {
+                    num_leds: decoder.u16()? as usize,
+                    pin: decoder.u8()?,
+                    timings: Timings {
+                        t0h: decoder.u16()?,
+                        t0l: decoder.u16()?,
+                        t1h: decoder.u16()?,
+                        t1l: decoder.u16()?,
+                        reset: decoder.u16()?,
+                    },
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let brightness_curve = match decoder.u8()? {
+            LINEAR => BrightnessCurve::Linear,
+            GAMMA => BrightnessCurve::Gamma(f32::from_bits(decoder.u32()?)),
+            CIE => BrightnessCurve::Cie,
+            _ => None?,
+        };
+        let power_on = match decoder.u8()? {
+            RESTORE => PowerOn::Restore,
+            COLOR => PowerOn::Color(decode_color(decoder)?),
+            OFF => PowerOn::Off,
+            _ => None?,
+        };
+
+        // the flash may hold a configuration of a build with other limits
+        Some(Self {
+            strips,
+            brightness_curve,
+            power_on,
+        })
+        .filter(Self::is_valid)
+    }
+}
+
+impl Setting for WifiCredentials {
+    const KEY: u8 = 2;
+    const VERSION: u8 = 1;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.ssid).str(&self.password);
+    }
+
+    fn decode(_: u8, decoder: &mut Decoder) -> Option<Self> {
+        Some(Self {
+            ssid: decoder.str()?,
+            password: decoder.str()?,
+        })
+    }
+}
+
+impl Setting for LocalTimeZone {
+    const KEY: u8 = 4;
+    const VERSION: u8 = 1;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.0);
+    }
+
+    fn decode(_: u8, decoder: &mut Decoder) -> Option<Self> {
+        decoder.str().map(Self)
+    }
+}
+
+// first byte of the request
+const SET: u8 = 0;
+const CYCLE: u8 = 1;
+const KEYFRAMES: u8 = 2;
+const SUN: u8 = 3;
+// strip index of requests to all strips
+const ALL_STRIPS: u8 = u8::MAX;
+
+impl Setting for LastRequest {
+    const KEY: u8 = 3;
+    const VERSION: u8 = 1;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        match self.target.strip {
+            StripTarget::All => encoder.u8(ALL_STRIPS),
+            StripTarget::Only(index) => encoder.u8(index.min(ALL_STRIPS as usize - 1) as u8),
+        };
+        match &self.target.segment {
+            Some(segment) => encoder.u8(1).str(segment),
+            None => encoder.u8(0),
+        };
+
+        match &self.request {
+            LedRequest::Set(color, duration, transition) => {
+                encoder.u8(SET);
+                encode_color(encoder, *color);
+                encoder.u64(duration.as_millis());
+                encode_transition(encoder, *transition);
+            }
+            LedRequest::DaylightCycle(on_color, current_time, ranges, transition) => {
+                encoder.u8(CYCLE);
+                encode_color(encoder, *on_color);
+                encode_current_time(encoder, *current_time);
+                for i in 0..4 {
+                    encoder.u64(ranges[i]);
+                }
+                encode_transition(encoder, *transition);
+            }
+            LedRequest::KeyframeCycle(current_time, keyframes) => {
+                encoder.u8(KEYFRAMES);
+                encode_current_time(encoder, *current_time);
+                encoder.u8(keyframes.len() as u8);
+                for keyframe in keyframes.iter() {
+                    encoder.u64(keyframe.minute);
+                    encode_color(encoder, keyframe.color);
+                    encode_transition(encoder, keyframe.transition);
+                }
+            }
+            LedRequest::SunCycle(current_time, sun) => {
+                encoder.u8(SUN);
+                encode_current_time(encoder, *current_time);
+                encoder
+                    .u32(sun.location.latitude.to_bits())
+                    .u32(sun.location.longitude.to_bits())
+                    .i32(sun.sunrise_offset.into())
+                    .i32(sun.sunset_offset.into());
+                encode_color(encoder, sun.on_color);
+                encode_transition(encoder, sun.transition);
+            }
+        }
+    }
+
+    fn decode(_: u8, decoder: &mut Decoder) -> Option<Self> {
+        let strip = match decoder.u8()? {
+            ALL_STRIPS => StripTarget::All,
+            index => StripTarget::Only(index as usize),
+        };
+        let segment = match decoder.u8()? {
+            0 => None,
+            _ => Some(decoder.str()?),
+        };
+
+        let request = match decoder.u8()? {
+            SET => LedRequest::Set(
+                decode_color(decoder)?,
+                Duration::from_millis(decoder.u64()?),
+                decode_transition(decoder)?,
+            ),
+            CYCLE => {
+                let on_color = decode_color(decoder)?;
+                let current_time = decode_current_time(decoder)?;
+                let mut minutes = [0; 4];
+                for minute in &mut minutes {
+                    *minute = decoder.u64()?;
+                }
+                let ranges = OverlapRanges::new(minutes).ok()?;
+                LedRequest::DaylightCycle(
+                    on_color,
+                    current_time,
+                    ranges,
+                    decode_transition(decoder)?,
+                )
+            }
+            KEYFRAMES => {
+                let current_time = decode_current_time(decoder)?;
+                let mut keyframes = [Keyframe::default(); MAX_KEYFRAMES];
+                let len = decoder.u8()? as usize;
+                for keyframe in keyframes.get_mut(..len)? {
+                    *keyframe = Keyframe {
+                        minute: decoder.u64()?,
+                        color: decode_color(decoder)?,
+                        transition: decode_transition(decoder)?,
+                    };
+                }
+                LedRequest::KeyframeCycle(current_time, Keyframes::new(&keyframes[..len]).ok()?)
+            }
+            SUN => LedRequest::SunCycle(
+                decode_current_time(decoder)?,
+                SunSchedule {
+                    location: Location {
+                        latitude: f32::from_bits(decoder.u32()?),
+                        longitude: f32::from_bits(decoder.u32()?),
+                    },
+                    sunrise_offset: decoder.i32()?.try_into().ok()?,
+                    sunset_offset: decoder.i32()?.try_into().ok()?,
+                    on_color: decode_color(decoder)?,
+                    transition: decode_transition(decoder)?,
+                },
+            ),
+            _ => None?,
+        };
+
+        Some(Self {
+            target: Target { strip, segment },
+            request,
+        })
+    }
+}
+
+fn encode_time(encoder: &mut Encoder, time: DateTime<FixedOffset>) {
+    encoder
+        .i64(time.timestamp())
+        .i32(time.offset().local_minus_utc());
+}
+
+fn decode_time(decoder: &mut Decoder) -> Option<DateTime<FixedOffset>> {
+    let (timestamp, offset) = (decoder.i64()?, decoder.i32()?);
+    FixedOffset::east_opt(offset)?
+        .timestamp_opt(timestamp, 0)
+        .single()
+}
+
+// the optional time of cycles, after a flag
+fn encode_current_time(encoder: &mut Encoder, time: Option<DateTime<FixedOffset>>) {
+    match time {
+        Some(time) => encode_time(encoder.u8(1), time),
+        None => {
+            encoder.u8(0);
+        }
+    }
+}
+
+// `None` if the time cannot be read, `Some(None)` if there is none
+fn decode_current_time(decoder: &mut Decoder) -> Option<Option<DateTime<FixedOffset>>> {
+    match decoder.u8()? {
+        0 => Some(None),
+        _ => decode_time(decoder).map(Some),
+    }
+}
+
+fn encode_color(encoder: &mut Encoder, color: Color) {
+    for channel in color.rgb16() {
+        encoder.u16(channel);
+    }
+}
+
+fn decode_color(decoder: &mut Decoder) -> Option<Color> {
+    Some(Color::new16(decoder.u16()?, decoder.u16()?, decoder.u16()?))
+}
+
+const COLOR_SPACES: [ColorSpace; 2] = [ColorSpace::Rgb, ColorSpace::Oklab];
+const EASINGS: [Easing; 7] = [
+    Easing::Linear,
+    Easing::EaseIn,
+    Easing::EaseOut,
+    Easing::EaseInOut,
+    Easing::Sine,
+    Easing::Cubic,
+    Easing::Smoothstep,
+];
+
+fn encode_transition(encoder: &mut Encoder, transition: Transition) {
+    encoder
+        .u8(transition.color_space as u8)
+        .u8(transition.easing as u8);
+}
+
+fn decode_transition(decoder: &mut Decoder) -> Option<Transition> {
+    Some(Transition {
+        color_space: *COLOR_SPACES.get(decoder.u8()? as usize)?,
+        easing: *EASINGS.get(decoder.u8()? as usize)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{MemFlash, SettingsStore};
+
+    #[test]
+    fn stores_device_settings() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let config = DeviceConfig::parse_json(
+            r#"{"strips": [{"num_leds": 30, "pin": 3},
+                {"num_leds": 8, "pin": 5, "timings": {"t0h": 400, "t0l": 850, "t1h": 800, "t1l": 450, "reset": 80}}],
+                "brightness_curve": {"gamma": 2.5}, "power_on": {"color": [255, 0, 0]}}"#,
+        )
+        .unwrap();
+        let credentials = WifiCredentials {
+            ssid: "home".into(),
+            password: "secret".into(),
+        };
+
+        store.save(&config).unwrap();
+        store.save(&credentials).unwrap();
+
+        assert_eq!(store.load::<DeviceConfig>().unwrap(), Some(config));
+        assert_eq!(store.load::<WifiCredentials>().unwrap(), Some(credentials));
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let valid =
+            DeviceConfig::parse_json(r#"{"strips": [{"num_leds": 12, "pin": 3}]}"#).unwrap();
+        let mut empty = valid.clone();
+        empty.strips[0].num_leds = 0;
+        let mut same_pins = valid.clone();
+        same_pins.strips.push(valid.strips[0].clone());
+
+        for config in [empty, same_pins] {
+            let mut payload = Vec::new();
+            config.encode(&mut Encoder::new(&mut payload));
+            let decoded = DeviceConfig::decode(DeviceConfig::VERSION, &mut Decoder::new(&payload));
+            assert_eq!(decoded, None);
+        }
+    }
+
+    #[test]
+    fn stores_keyframe_cycles() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let body = r##"{"type": "keyframes", "keyframes": [{"minute": 1200, "color": [20, 30, 90]},
+            {"minute": 360, "color": "#8B0000", "easing": "sine", "color_space": "oklab"},
+            {"minute": 600, "color": {"kelvin": 4500}}]}"##;
+        let last = LastRequest {
+            target: LedRequest::parse_target(JSONValue::load(body)).unwrap(),
+            request: LedRequest::parse_json(body).unwrap(),
+        };
+
+        store.save(&last).unwrap();
+        let LastRequest { request, .. } = store.load().unwrap().unwrap();
+
+        let (
+            LedRequest::KeyframeCycle(time, keyframes),
+            LedRequest::KeyframeCycle(None, saved_keyframes),
+        ) = (request, last.request)
+        else {
+            panic!("expected a keyframe cycle request");
+        };
+        assert_eq!(time, None);
+        assert!(keyframes.iter().eq(saved_keyframes.iter()));
+        assert_eq!(keyframes[1].transition.easing, Easing::Sine);
+    }
+
+    #[test]
+    fn stores_sun_cycles() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let body = r#"{"type": "sun", "on_color": {"kelvin": 3000}, "latitude": 52.52, "longitude": 13.405,
+            "sunrise_offset": -20, "sunset_offset": 45, "color_space": "oklab"}"#;
+        let last = LastRequest {
+            target: LedRequest::parse_target(JSONValue::load(body)).unwrap(),
+            request: LedRequest::parse_json(body).unwrap(),
+        };
+
+        store.save(&last).unwrap();
+        let LastRequest { request, .. } = store.load().unwrap().unwrap();
+
+        let (LedRequest::SunCycle(None, sun), LedRequest::SunCycle(None, saved_sun)) =
+            (request, last.request)
+        else {
+            panic!("expected a sun cycle request");
+        };
+        assert_eq!(sun, saved_sun);
+    }
+
+    #[test]
+    fn stores_time_zones() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let zone = LocalTimeZone::parse("<+0330>-3:30").unwrap();
+
+        store.save(&zone).unwrap();
+        let saved = store.load::<LocalTimeZone>().unwrap().unwrap();
+        assert_eq!(saved, zone);
+        assert_eq!(
+            saved
+                .rule()
+                .unwrap()
+                .offset_at(&Default::default())
+                .local_minus_utc(),
+            12600
+        );
+
+        assert_eq!(LocalTimeZone::parse(""), Some(LocalTimeZone("".into())));
+        assert_eq!(LocalTimeZone::parse("CET-1CEST,M3.5.0"), None);
+    }
+
+    #[test]
+    fn stores_requests() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let body = r#"{"type": "cycle", "on_color": {"kelvin": 2700}, "current_time": "2014-11-28T21:00:09+09:00",
+            "cycle_minutes": [540, 600, 1260, 1320], "easing": "sine", "segment": "top"}"#;
+        let last = LastRequest {
+            target: LedRequest::parse_target(JSONValue::load(body)).unwrap(),
+            request: LedRequest::parse_json(body).unwrap(),
+        };
+
+        store.save(&last).unwrap();
+        let LastRequest { target, request } = store.load().unwrap().unwrap();
+
+        assert_eq!(target, last.target);
+        let (
+            LedRequest::DaylightCycle(color, time, ranges, transition),
+            LedRequest::DaylightCycle(saved_color, saved_time, _, saved_transition),
+        ) = (request, last.request)
+        else {
+            panic!("expected a cycle request");
+        };
+        assert_eq!(color, saved_color);
+        assert_eq!(time, saved_time);
+        assert_eq!(time.unwrap().offset().local_minus_utc(), 9 * 60 * 60);
+        assert_eq!([ranges[0], ranges[3]], [540, 1320]);
+        assert_eq!(transition, saved_transition);
+    }
+}
@@ -0,0 +1,418 @@
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use super::{Decoder, Encoder};
+
+// marks sectors written by the store, followed by a sequence number that grows with every compaction
+const MAGIC: u32 = u32::from_le_bytes(*b"LAMP");
+const SECTOR_HEADER: usize = 8;
+// key, version, payload length and CRC
+const RECORD_HEADER: usize = 8;
+// records start on a fresh word, so no word is written twice between erases
+const ALIGN: usize = 4;
+// erased flash, marks the free space after the last record
+const FREE_KEY: u8 = 0xFF;
+
+// version and payload of a record
+type Payload = (u8, Vec<u8>);
+
+/// A value kept in the settings store.
+pub trait Setting: Sized {
+    /// Identifies the records of the setting, 0xFF is reserved.
+    const KEY: u8;
+    /// Format of the payload, increased whenever it changes.
+    const VERSION: u8;
+
+    fn encode(&self, encoder: &mut Encoder);
+
+    /// Reads a payload written with `version`.
+    /// Payloads of older versions are migrated, `None` drops the record.
+    fn decode(version: u8, decoder: &mut Decoder) -> Option<Self>;
+}
+
+#[derive(Debug)]
+pub enum StoreError<E> {
+    Flash(E),
+    /// The store needs at least two erase sectors and a write size of at most 4 bytes.
+    Unsupported,
+    /// The setting does not fit into a sector next to the others.
+    TooLarge,
+}
+
+/// Settings kept as a log of records in NOR flash.
+///
+/// Saving appends a record to the active sector, the latest record of a key wins.
+/// A full sector is compacted into the next one, which only becomes active once
+/// its header is written, so a power loss at any point keeps the previous settings.
+/// The sectors are used in turn, spreading the erases over the whole flash.
+pub struct SettingsStore<F> {
+    flash: F,
+    sector: usize,
+    sequence: u32,
+    // offset of the free space in the active sector
+    end: usize,
+}
+
+impl<F: NorFlash> SettingsStore<F> {
+    /// Opens the store on `flash`, formatting it when it holds no settings yet.
+    pub fn new(flash: F) -> Result<Self, StoreError<F::Error>> {
+        if flash.capacity() / F::ERASE_SIZE < 2 || !ALIGN.is_multiple_of(F::WRITE_SIZE) {
+            Err(StoreError::Unsupported)?
+        }
+
+        let mut store = Self {
+            flash,
+            sector: 0,
+            sequence: 0,
+            end: SECTOR_HEADER,
+        };
+
+        let mut active: Option<(usize, u32)> = None;
+        for sector in 0..store.num_sectors() {
+            let mut header = [0; SECTOR_HEADER];
+            store.read(sector, 0, &mut header)?;
+            let (magic, sequence) = header.split_at(4);
+            let sequence = u32::from_le_bytes(sequence.try_into().unwrap());
+            if magic == MAGIC.to_le_bytes() && active.is_none_or(|(_, newest)| sequence > newest) {
+                active = Some((sector, sequence));
+            }
+        }
+
+        match active {
+            Some((sector, sequence)) => {
+                store.sector = sector;
+                store.sequence = sequence;
+                store.end = store.scan(|_, _, _| {})?;
+            }
+            None => {
+                store.erase(0)?;
+                store.write(0, 0, &sector_header(1))?;
+                store.sequence = 1;
+            }
+        }
+
+        Ok(store)
+    }
+
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    /// Latest saved value of the setting, `None` if there is none or it cannot be decoded.
+    pub fn load<S: Setting>(&mut self) -> Result<Option<S>, StoreError<F::Error>> {
+        Ok(self
+            .latest(S::KEY)?
+            .and_then(|(version, payload)| S::decode(version, &mut Decoder::new(&payload))))
+    }
+
+    /// Saves the setting, values equal to the stored one are not written again.
+    pub fn save<S: Setting>(&mut self, value: &S) -> Result<(), StoreError<F::Error>> {
+        let mut payload = Vec::new();
+        value.encode(&mut Encoder::new(&mut payload));
+        if RECORD_HEADER + align(payload.len()) > F::ERASE_SIZE - SECTOR_HEADER {
+            Err(StoreError::TooLarge)?
+        }
+
+        if self.latest(S::KEY)? == Some((S::VERSION, payload.clone())) {
+            return Ok(());
+        }
+
+        let record = encode_record(S::KEY, S::VERSION, &payload);
+        match self.end + record.len() <= F::ERASE_SIZE {
+            true => {
+                self.write(self.sector, self.end, &record)?;
+                self.end += record.len();
+                Ok(())
+            }
+            false => self.compact(S::KEY, record),
+        }
+    }
+
+    // moves the latest record of every key into the next sector, with `record` replacing the one of `key`
+    fn compact(&mut self, key: u8, record: Vec<u8>) -> Result<(), StoreError<F::Error>> {
+        let mut records: Vec<(u8, Vec<u8>)> = Vec::new();
+        self.scan(|key, version, payload| {
+            let record = encode_record(key, version, payload);
+            match records.iter_mut().find(|(other, _)| *other == key) {
+                Some((_, latest)) => *latest = record,
+                None => records.push((key, record)),
+            }
+        })?;
+        records.retain(|(other, _)| *other != key);
+        records.push((key, record));
+
+        let next = (self.sector + 1) % self.num_sectors();
+        self.erase(next)?;
+        let mut end = SECTOR_HEADER;
+        for (_, record) in &records {
+            if end + record.len() > F::ERASE_SIZE {
+                Err(StoreError::TooLarge)?
+            }
+            self.write(next, end, record)?;
+            end += record.len();
+        }
+        // written last, until then the previous sector stays the active one
+        self.write(next, 0, &sector_header(self.sequence + 1))?;
+
+        self.sector = next;
+        self.sequence += 1;
+        self.end = end;
+        Ok(())
+    }
+
+    fn latest(&mut self, key: u8) -> Result<Option<Payload>, StoreError<F::Error>> {
+        let mut latest = None;
+        self.scan(|other, version, payload| {
+            if other == key {
+                latest = Some((version, payload.to_vec()));
+            }
+        })?;
+        Ok(latest)
+    }
+
+    // passes the valid records of the active sector in order to `record`, returns where the free space starts
+    fn scan(
+        &mut self,
+        mut record: impl FnMut(u8, u8, &[u8]),
+    ) -> Result<usize, StoreError<F::Error>> {
+        let mut pos = SECTOR_HEADER;
+        while pos + RECORD_HEADER <= F::ERASE_SIZE {
+            let mut header = [0; RECORD_HEADER];
+            self.read(self.sector, pos, &mut header)?;
+            if header == [0xFF; RECORD_HEADER] {
+                break;
+            }
+
+            let [key, version, len @ .., _, _, _, _] = header;
+            let len = u16::from_le_bytes(len) as usize;
+            let size = RECORD_HEADER + align(len);
+            if key == FREE_KEY || pos + size > F::ERASE_SIZE {
+                // a header cut short by a power loss, the rest of the sector is left unused
+                pos = F::ERASE_SIZE;
+                break;
+            }
+
+            let mut payload = alloc::vec![0; len];
+            self.read(self.sector, pos + RECORD_HEADER, &mut payload)?;
+            // records with a wrong CRC were cut short and are skipped
+            if header[4..] == crc32(&header[..4], &payload).to_le_bytes() {
+                record(key, version, &payload);
+            }
+            pos += size;
+        }
+        Ok(pos)
+    }
+
+    fn num_sectors(&self) -> usize {
+        self.flash.capacity() / F::ERASE_SIZE
+    }
+
+    fn address(sector: usize, offset: usize) -> u32 {
+        (sector * F::ERASE_SIZE + offset) as u32
+    }
+
+    fn read(
+        &mut self,
+        sector: usize,
+        offset: usize,
+        bytes: &mut [u8],
+    ) -> Result<(), StoreError<F::Error>> {
+        self.flash
+            .read(Self::address(sector, offset), bytes)
+            .map_err(StoreError::Flash)
+    }
+
+    fn write(
+        &mut self,
+        sector: usize,
+        offset: usize,
+        bytes: &[u8],
+    ) -> Result<(), StoreError<F::Error>> {
+        self.flash
+            .write(Self::address(sector, offset), bytes)
+            .map_err(StoreError::Flash)
+    }
+
+    fn erase(&mut self, sector: usize) -> Result<(), StoreError<F::Error>> {
+        self.flash
+            .erase(Self::address(sector, 0), Self::address(sector + 1, 0))
+            .map_err(StoreError::Flash)
+    }
+}
+
+fn align(len: usize) -> usize {
+    len.next_multiple_of(ALIGN)
+}
+
+fn sector_header(sequence: u32) -> [u8; SECTOR_HEADER] {
+    let mut header = [0; SECTOR_HEADER];
+    header[..4].copy_from_slice(&MAGIC.to_le_bytes());
+    header[4..].copy_from_slice(&sequence.to_le_bytes());
+    header
+}
+
+// header and payload padded with erased bytes, written at once
+fn encode_record(key: u8, version: u8, payload: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER + align(payload.len()));
+    record.extend_from_slice(&[key, version]);
+    record.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    let crc = crc32(&record, payload);
+    record.extend_from_slice(&crc.to_le_bytes());
+    record.extend_from_slice(payload);
+    record.resize(RECORD_HEADER + align(payload.len()), 0xFF);
+    record
+}
+
+// CRC-32 (IEEE) over the record header and payload
+fn crc32(header: &[u8], payload: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in header.iter().chain(payload) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::MemFlash;
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    impl Setting for Counter {
+        const KEY: u8 = 0x10;
+        const VERSION: u8 = 2;
+
+        fn encode(&self, encoder: &mut Encoder) {
+            encoder.u32(self.0);
+        }
+
+        fn decode(version: u8, decoder: &mut Decoder) -> Option<Self> {
+            match version {
+                // the first version counted in a single byte
+                1 => decoder.u8().map(|count| Counter(count as u32)),
+                2 => decoder.u32().map(Counter),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Name(alloc::string::String);
+
+    impl Setting for Name {
+        const KEY: u8 = 0x11;
+        const VERSION: u8 = 1;
+
+        fn encode(&self, encoder: &mut Encoder) {
+            encoder.str(&self.0);
+        }
+
+        fn decode(_: u8, decoder: &mut Decoder) -> Option<Self> {
+            decoder.str().map(Name)
+        }
+    }
+
+    fn reopen(store: SettingsStore<MemFlash>) -> SettingsStore<MemFlash> {
+        SettingsStore::new(store.flash).unwrap()
+    }
+
+    #[test]
+    fn keeps_settings_over_restarts() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        assert_eq!(store.load::<Counter>().unwrap(), None);
+
+        store.save(&Counter(5)).unwrap();
+        store.save(&Name("lamp".into())).unwrap();
+        store.save(&Counter(6)).unwrap();
+
+        let mut store = reopen(store);
+        assert_eq!(store.load::<Counter>().unwrap(), Some(Counter(6)));
+        assert_eq!(store.load::<Name>().unwrap(), Some(Name("lamp".into())));
+    }
+
+    #[test]
+    fn spreads_erases_over_sectors() {
+        let mut store = SettingsStore::new(MemFlash::new(4)).unwrap();
+        store.save(&Name("lamp".into())).unwrap();
+        for count in 0..10_000 {
+            store.save(&Counter(count)).unwrap();
+        }
+
+        let mut store = reopen(store);
+        assert_eq!(store.load::<Counter>().unwrap(), Some(Counter(9_999)));
+        assert_eq!(store.load::<Name>().unwrap(), Some(Name("lamp".into())));
+
+        let erases = store.flash().erase_counts();
+        let (min, max) = (erases.iter().min().unwrap(), erases.iter().max().unwrap());
+        assert!(*min > 0 && max - min <= 1, "{erases:?}");
+    }
+
+    #[test]
+    fn skips_unchanged_settings() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        for _ in 0..10_000 {
+            store.save(&Counter(1)).unwrap();
+        }
+        assert_eq!(store.end, SECTOR_HEADER + RECORD_HEADER + 4);
+        assert_eq!(store.flash().erase_counts(), [1, 0]);
+    }
+
+    #[test]
+    fn ignores_records_cut_short() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        store.save(&Counter(1)).unwrap();
+        store.save(&Counter(2)).unwrap();
+
+        // the last payload byte of the second record never made it to flash
+        let end = store.end;
+        store.flash.data_mut()[end - 1] = 0xFF;
+
+        let mut store = reopen(store);
+        assert_eq!(store.load::<Counter>().unwrap(), Some(Counter(1)));
+        store.save(&Counter(3)).unwrap();
+        assert_eq!(reopen(store).load::<Counter>().unwrap(), Some(Counter(3)));
+    }
+
+    #[test]
+    fn keeps_the_previous_sector_until_compacted() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let mut count = 0;
+        while store.sector == 0 {
+            count += 1;
+            store.save(&Counter(count)).unwrap();
+        }
+
+        // power lost before the header of the new sector was written
+        store.flash.data_mut()[MemFlash::SECTOR_SIZE..][..SECTOR_HEADER].fill(0xFF);
+        assert_eq!(
+            reopen(store).load::<Counter>().unwrap(),
+            Some(Counter(count - 1))
+        );
+    }
+
+    #[test]
+    fn migrates_old_versions() {
+        let mut store = SettingsStore::new(MemFlash::new(2)).unwrap();
+        let record = encode_record(Counter::KEY, 1, &[7]);
+        store.write(0, SECTOR_HEADER, &record).unwrap();
+
+        assert_eq!(reopen(store).load::<Counter>().unwrap(), Some(Counter(7)));
+    }
+
+    #[test]
+    fn rejects_unsupported_flash() {
+        assert!(matches!(
+            SettingsStore::new(MemFlash::new(1)),
+            Err(StoreError::Unsupported)
+        ));
+    }
+}
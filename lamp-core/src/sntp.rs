@@ -0,0 +1,215 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use embassy_time::Duration;
+
+/// Size of an SNTP packet without extension fields.
+pub const PACKET_SIZE: usize = 48;
+/// UDP port NTP servers listen on.
+pub const PORT: u16 = 123;
+
+// seconds from the NTP epoch (1900-01-01) to the Unix epoch
+const UNIX_OFFSET: i64 = 2_208_988_800;
+// seconds covered by one era of 32-bit NTP timestamps
+const ERA: i64 = 1 << 32;
+
+// leap indicator 0, version 4, mode 3 (client)
+const CLIENT_HEADER: u8 = 0b00_100_011;
+const MODE_SERVER: u8 = 4;
+const LEAP_ALARM: u8 = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SntpError {
+    /// The packet is too short or was not sent by a server.
+    Malformed,
+    /// The packet does not answer our request.
+    Unexpected,
+    /// The server has no time to give, e.g. a kiss-of-death packet.
+    Unsynchronized,
+}
+
+/// A time request sent to an NTP server (RFC 4330).
+///
+/// The server echoes the transmit timestamp of the request, so it is set to `cookie`
+/// to tell the response to this request apart from stray or late packets.
+pub struct SntpRequest {
+    cookie: u64,
+}
+
+impl SntpRequest {
+    pub fn new(cookie: u64) -> Self {
+        Self { cookie }
+    }
+
+    pub fn packet(&self) -> [u8; PACKET_SIZE] {
+        let mut packet = [0; PACKET_SIZE];
+        packet[0] = CLIENT_HEADER;
+        packet[40..48].copy_from_slice(&self.cookie.to_be_bytes());
+        packet
+    }
+
+    /// Returns the UTC time at which `packet` was received, `round_trip` after the request was sent.
+    ///
+    /// Half of the round trip, less the time the server held the request, is added as the
+    /// delay of the response.
+    pub fn parse_response(
+        &self,
+        packet: &[u8],
+        round_trip: Duration,
+    ) -> Result<DateTime<Utc>, SntpError> {
+        let Some(packet) = packet.first_chunk::<PACKET_SIZE>() else {
+            return Err(SntpError::Malformed);
+        };
+
+        let leap = packet[0] >> 6;
+        let version = (packet[0] >> 3) & 0b111;
+        let mode = packet[0] & 0b111;
+        let stratum = packet[1];
+        if mode != MODE_SERVER || !(1..=4).contains(&version) {
+            Err(SntpError::Malformed)?
+        }
+        if timestamp(packet, 24) != self.cookie {
+            Err(SntpError::Unexpected)?
+        }
+        if leap == LEAP_ALARM || !(1..=15).contains(&stratum) {
+            Err(SntpError::Unsynchronized)?
+        }
+
+        let received = to_datetime(timestamp(packet, 32)).ok_or(SntpError::Malformed)?;
+        let transmitted = to_datetime(timestamp(packet, 40)).ok_or(SntpError::Malformed)?;
+
+        let round_trip = TimeDelta::microseconds(round_trip.as_micros() as i64);
+        let delay = (round_trip - (transmitted - received)).max(TimeDelta::zero());
+        Ok(transmitted + delay / 2)
+    }
+}
+
+fn timestamp(packet: &[u8; PACKET_SIZE], at: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&packet[at..at + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+// NTP timestamps count 32-bit seconds and fractions from 1900, wrapping in 2036;
+// seconds with the top bit cleared are taken to be in the next era
+fn to_datetime(timestamp: u64) -> Option<DateTime<Utc>> {
+    let seconds = (timestamp >> 32) as i64;
+    let seconds = match seconds & (1 << 31) {
+        0 => seconds + ERA,
+        _ => seconds,
+    };
+    let nanos = ((timestamp & 0xFFFF_FFFF) * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(seconds - UNIX_OFFSET, nanos as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_timestamp(time: DateTime<Utc>) -> u64 {
+        let seconds = (time.timestamp() + UNIX_OFFSET) as u64 & 0xFFFF_FFFF;
+        let fraction = ((time.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+        (seconds << 32) | fraction
+    }
+
+    // stands in for an NTP server that receives `request` at `received` and answers at `transmitted`
+    fn serve(
+        request: &[u8; PACKET_SIZE],
+        stratum: u8,
+        received: DateTime<Utc>,
+        transmitted: DateTime<Utc>,
+    ) -> [u8; PACKET_SIZE] {
+        let mut response = [0; PACKET_SIZE];
+        response[0] = 0b00_100_100;
+        response[1] = stratum;
+        response[24..32].copy_from_slice(&request[40..48]);
+        response[32..40].copy_from_slice(&to_timestamp(received).to_be_bytes());
+        response[40..48].copy_from_slice(&to_timestamp(transmitted).to_be_bytes());
+        response
+    }
+
+    fn time(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn builds_client_requests() {
+        let packet = SntpRequest::new(0x0123_4567_89AB_CDEF).packet();
+
+        assert_eq!(packet[0], 0x23);
+        assert!(packet[1..40].iter().all(|&byte| byte == 0));
+        assert_eq!(
+            packet[40..48],
+            [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]
+        );
+    }
+
+    #[test]
+    fn reads_time_from_server() {
+        let request = SntpRequest::new(42);
+        let now = time("2025-06-01T12:00:00.250Z");
+        let response = serve(&request.packet(), 2, now, now);
+
+        assert_eq!(
+            request.parse_response(&response, Duration::from_ticks(0)),
+            Ok(now)
+        );
+    }
+
+    #[test]
+    fn adds_half_of_the_network_delay() {
+        let request = SntpRequest::new(42);
+        // 750 ms round trip of which the server held the request for 250 ms
+        let response = serve(
+            &request.packet(),
+            2,
+            time("2025-06-01T12:00:00Z"),
+            time("2025-06-01T12:00:00.250Z"),
+        );
+
+        assert_eq!(
+            request.parse_response(&response, Duration::from_millis(750)),
+            Ok(time("2025-06-01T12:00:00.500Z"))
+        );
+    }
+
+    #[test]
+    fn reads_times_after_the_era_rollover() {
+        let request = SntpRequest::new(42);
+        let now = time("2040-01-01T00:00:00Z");
+        let response = serve(&request.packet(), 1, now, now);
+
+        assert_eq!(
+            request.parse_response(&response, Duration::from_ticks(0)),
+            Ok(now)
+        );
+    }
+
+    #[test]
+    fn rejects_other_responses() {
+        let request = SntpRequest::new(42);
+        let now = time("2025-06-01T12:00:00Z");
+
+        let stray = serve(&SntpRequest::new(7).packet(), 2, now, now);
+        assert_eq!(
+            request.parse_response(&stray, Duration::from_ticks(0)),
+            Err(SntpError::Unexpected)
+        );
+
+        let kiss_of_death = serve(&request.packet(), 0, now, now);
+        assert_eq!(
+            request.parse_response(&kiss_of_death, Duration::from_ticks(0)),
+            Err(SntpError::Unsynchronized)
+        );
+
+        let mut client = serve(&request.packet(), 2, now, now);
+        client[0] = CLIENT_HEADER;
+        assert_eq!(
+            request.parse_response(&client, Duration::from_ticks(0)),
+            Err(SntpError::Malformed)
+        );
+
+        assert_eq!(
+            request.parse_response(&client[..40], Duration::from_ticks(0)),
+            Err(SntpError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use core::cell::{Cell, RefCell};
+
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use embassy_time::{Duration, Instant};
+
+use super::{
+    global_time::{GlobalInstant, GlobalTime},
+    time_zone::TzRule,
+};
+
+/// Clock of the lamp read by effects: the local chip time and, once it was set,
+/// the wall time shared by all of them.
+pub trait Clock {
+    fn now(&self) -> Instant;
+
+    /// Wall time of the lamp, `None` until it was set.
+    fn wall_time(&self) -> Option<GlobalInstant<TzRule>>;
+}
+
+/// Wall time kept against the chip time of a [`Clock`].
+///
+/// Local time follows the time zone rule when one is set,
+/// otherwise the UTC offset the time was last set with.
+#[derive(Default)]
+pub struct WallTime {
+    time: Option<GlobalTime<TzRule>>,
+    zone: Option<TzRule>,
+}
+
+impl WallTime {
+    pub const fn new() -> Self {
+        Self {
+            time: None,
+            zone: None,
+        }
+    }
+
+    pub fn set(&mut self, datetime: DateTime<FixedOffset>, clock: &impl Clock) {
+        let zone = self
+            .zone
+            .unwrap_or_else(|| TzRule::fixed(*datetime.offset()));
+        self.time = Some(GlobalTime::at(datetime.with_timezone(&zone), clock));
+    }
+
+    /// Sets the time from a UTC one, keeping the local time of the lamp in the zone it was in.
+    pub fn set_utc(&mut self, utc: DateTime<Utc>, clock: &impl Clock) {
+        let zone = self.zone.unwrap_or_else(|| match self.now(clock) {
+            Some(now) => now.datetime().timezone(),
+            None => TzRule::fixed(Utc.fix()),
+        });
+        self.time = Some(GlobalTime::at(utc.with_timezone(&zone), clock));
+    }
+
+    /// Moves local time to the rule of `zone`, or keeps its current UTC offset without one.
+    pub fn set_zone(&mut self, zone: Option<TzRule>, clock: &impl Clock) {
+        self.zone = zone;
+        if let Some(now) = self.now(clock) {
+            let now = now.datetime();
+            let zone = zone.unwrap_or_else(|| TzRule::fixed(now.offset().fix()));
+            self.time = Some(GlobalTime::at(now.with_timezone(&zone), clock));
+        }
+    }
+
+    pub fn now(&self, clock: &impl Clock) -> Option<GlobalInstant<TzRule>> {
+        self.time.as_ref().map(|time| time.now(clock))
+    }
+}
+
+/// Clock that only moves when told to, used for tests and simulation.
+pub struct ManualClock {
+    now: Cell<Instant>,
+    time: RefCell<WallTime>,
+}
+
+impl ManualClock {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            now: Cell::new(start),
+            time: RefCell::new(WallTime::new()),
+        }
+    }
+
+    /// Sets the wall time at the current instant, it moves along with the clock from there.
+    pub fn set_wall_time(&self, datetime: DateTime<FixedOffset>) {
+        self.time.borrow_mut().set(datetime, self);
+    }
+
+    pub fn set_time_zone(&self, zone: Option<TzRule>) {
+        self.time.borrow_mut().set_zone(zone, self);
+    }
+
+    pub fn set(&self, instant: Instant) {
+        self.now.set(instant);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Instant::from_ticks(0))
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+
+    fn wall_time(&self) -> Option<GlobalInstant<TzRule>> {
+        self.time.borrow().now(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(rfc3339: &str) -> DateTime<FixedOffset> {
+        rfc3339.parse().unwrap()
+    }
+
+    fn local(clock: &ManualClock) -> DateTime<FixedOffset> {
+        clock.wall_time().unwrap().datetime().fixed_offset()
+    }
+
+    #[test]
+    fn keeps_local_time_in_the_zone() {
+        let clock = ManualClock::default();
+        assert!(clock.wall_time().is_none());
+
+        // without a zone the offset of the time is kept, also for UTC times
+        clock.set_wall_time(time("2025-03-29T12:00:00+09:00"));
+        clock
+            .time
+            .borrow_mut()
+            .set_utc(time("2025-03-29T04:00:00Z").with_timezone(&Utc), &clock);
+        assert_eq!(local(&clock).to_string(), "2025-03-29 13:00:00 +09:00");
+
+        // with one the local time moves to it and across its changes
+        let cet = TzRule::parse("CET-1CEST,M3.5.0,M10.5.0/3");
+        clock.set_time_zone(cet);
+        assert_eq!(local(&clock).to_string(), "2025-03-29 05:00:00 +01:00");
+        clock.advance(Duration::from_secs(24 * 60 * 60));
+        assert_eq!(local(&clock).to_string(), "2025-03-30 06:00:00 +02:00");
+
+        // times in other offsets do not change the zone
+        clock.set_wall_time(time("2025-07-01T12:00:00Z"));
+        assert_eq!(local(&clock).to_string(), "2025-07-01 14:00:00 +02:00");
+
+        clock.set_time_zone(None);
+        clock.advance(Duration::from_secs(180 * 24 * 60 * 60));
+        assert_eq!(local(&clock).offset().local_minus_utc(), 7200);
+    }
+}
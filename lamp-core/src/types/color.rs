@@ -0,0 +1,158 @@
+/// RGB color with 16 bits per channel, the output stage dithers it down to what the LEDs take
+/// and puts the channels in the order of the strip.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Color([u16; 3]);
+
+impl Color {
+    /// Creates a color from 8-bit channels, 255 maps to full 16-bit brightness.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self::new16(r as u16 * 257, g as u16 * 257, b as u16 * 257)
+    }
+
+    pub const fn new16(r: u16, g: u16, b: u16) -> Self {
+        Self([r, g, b])
+    }
+
+    /// Parses `#RRGGBB` or `RRGGBB` hex notation.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Creates a color from hue in degrees, saturation and value in `0.0..=1.0`.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = (hue % 360.0 + 360.0) % 360.0 / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - libm::fabsf(hue % 2.0 - 1.0));
+        let m = value - chroma;
+
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self::from_unit(r + m, g + m, b + m)
+    }
+
+    /// Approximates the color of a black body at `kelvin` (1000 K to 40000 K)
+    /// with the given `brightness` in `0.0..=1.0`.
+    pub fn from_kelvin(kelvin: u32, brightness: f32) -> Self {
+        // curve fit by Tanner Helland to the CIE 1964 10-degree color matching functions
+        let temp = kelvin.clamp(1000, 40000) as f32 / 100.0;
+
+        let r = if temp <= 66.0 {
+            255.0
+        } else {
+            329.69873 * libm::powf(temp - 60.0, -0.13320476)
+        };
+        let g = if temp <= 66.0 {
+            99.4708 * libm::logf(temp) - 161.11957
+        } else {
+            288.12216 * libm::powf(temp - 60.0, -0.07551485)
+        };
+        let b = if temp >= 66.0 {
+            255.0
+        } else if temp <= 19.0 {
+            0.0
+        } else {
+            138.51773 * libm::logf(temp - 10.0) - 305.0448
+        };
+
+        let scale = |c: f32| c.clamp(0.0, 255.0) / 255.0 * brightness;
+        Self::from_unit(scale(r), scale(g), scale(b))
+    }
+
+    fn from_unit(r: f32, g: f32, b: f32) -> Self {
+        let channel = |c: f32| libm::roundf(c.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        Self::new16(channel(r), channel(g), channel(b))
+    }
+
+    pub const fn black() -> Self {
+        Self([0, 0, 0])
+    }
+
+    pub const fn warm_white() -> Self {
+        Self::new(244, 255, 200)
+    }
+
+    pub fn interpolate(&self, other: Self, value: u64, max: u64) -> Self {
+        let mut new_color = self.0;
+        new_color
+            .iter_mut()
+            .zip(other.0)
+            .for_each(|(a, b)| *a = ((*a as u64 * (max - value) + b as u64 * value) / max) as u16);
+        Self(new_color)
+    }
+
+    pub fn rgb16(&self) -> [u16; 3] {
+        self.0
+    }
+
+    /// Channels truncated to 8 bits.
+    pub fn rgb(&self) -> [u8; 3] {
+        self.rgb16().map(|c| (c >> 8) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_8_bit_channels() {
+        assert_eq!(Color::new(0, 1, 255).rgb16(), [0, 257, 65535]);
+        assert_eq!(Color::new(0, 1, 255).rgb(), [0, 1, 255]);
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(Color::from_hex("#FFF4C8"), Some(Color::new(255, 244, 200)));
+        assert_eq!(Color::from_hex("00ff80"), Some(Color::new(0, 255, 128)));
+        assert_eq!(Color::from_hex("#FFF"), None);
+        assert_eq!(Color::from_hex("#GGGGGG"), None);
+        assert_eq!(Color::from_hex("#FFF4C8A"), None);
+    }
+
+    #[test]
+    fn converts_hsv() {
+        assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::new(255, 0, 0));
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::new(0, 255, 0));
+        assert_eq!(Color::from_hsv(-120.0, 1.0, 1.0), Color::new(0, 0, 255));
+        assert_eq!(Color::from_hsv(60.0, 1.0, 0.5).rgb(), [128, 128, 0]);
+        assert_eq!(Color::from_hsv(200.0, 0.0, 1.0), Color::new(255, 255, 255));
+    }
+
+    #[test]
+    fn converts_kelvin() {
+        let candle = Color::from_kelvin(1900, 1.0).rgb();
+        let warm = Color::from_kelvin(2700, 1.0).rgb();
+        let daylight = Color::from_kelvin(6600, 1.0).rgb();
+        let sky = Color::from_kelvin(10000, 1.0).rgb();
+
+        assert_eq!(candle, [255, 132, 0]);
+        assert_eq!(warm, [255, 167, 87]);
+        assert_eq!(daylight, [255, 255, 255]);
+        assert!(sky[0] < sky[1] && sky[1] < sky[2]);
+
+        assert_eq!(Color::from_kelvin(6600, 0.5).rgb(), [128, 128, 128]);
+    }
+
+    #[test]
+    fn interpolates_between_endpoints() {
+        let from = Color::new16(0, 100, 65535);
+        let to = Color::new16(65535, 100, 0);
+
+        assert_eq!(from.interpolate(to, 0, 10), from);
+        assert_eq!(from.interpolate(to, 10, 10), to);
+        assert_eq!(from.interpolate(to, 5, 10).rgb16(), [32767, 100, 32767]);
+    }
+}
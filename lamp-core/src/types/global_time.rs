@@ -0,0 +1,146 @@
+use chrono::{DateTime, MappedLocalTime, NaiveTime, Offset, TimeDelta, TimeZone, Timelike};
+use embassy_time::{Duration, Instant};
+
+use super::clock::Clock;
+
+pub struct GlobalTime<Tz: TimeZone> {
+    datetime: DateTime<Tz>,
+    instant: Instant, // local chip time when datetime was received
+}
+
+pub struct GlobalInstant<Tz: TimeZone> {
+    datetime: DateTime<Tz>,
+    elapsed: Duration,
+}
+
+impl<Tz: TimeZone> GlobalTime<Tz> {
+    pub fn at(datetime: DateTime<Tz>, clock: &impl Clock) -> Self {
+        Self {
+            instant: clock.now(),
+            datetime,
+        }
+    }
+
+    pub fn now(&self, clock: &impl Clock) -> GlobalInstant<Tz> {
+        GlobalInstant {
+            datetime: self.datetime.clone(),
+            elapsed: clock.now() - self.instant,
+        }
+    }
+}
+
+impl<Tz: TimeZone> GlobalInstant<Tz> {
+    pub fn datetime(&self) -> DateTime<Tz> {
+        self.datetime.clone() + TimeDelta::milliseconds(self.elapsed.as_millis() as i64)
+    }
+
+    /// Minute of the local day, which follows the changes of the time zone.
+    pub fn day_minute(&self) -> u64 {
+        (self.datetime().num_seconds_from_midnight() / 60).into()
+    }
+
+    /// Seconds until the local time is `minute` of the day next,
+    /// which may be more or less than a day away when the offset of the time zone changes.
+    pub fn secs_till_minute(&self, minute: u64) -> u64 {
+        let now = self.datetime();
+        let now = now.clone() - TimeDelta::nanoseconds(now.nanosecond().into());
+        let midnight = now.date_naive().and_time(NaiveTime::MIN);
+
+        // the minute may be 24:00, which is midnight of the next day
+        (0..3)
+            .map(|day| {
+                let local = midnight + TimeDelta::days(day) + TimeDelta::minutes(minute as i64);
+                match now.timezone().from_local_datetime(&local) {
+                    MappedLocalTime::Single(time) => time,
+                    MappedLocalTime::Ambiguous(first, second) => {
+                        if first >= now {
+                            first
+                        } else {
+                            second
+                        }
+                    }
+                    // skipped when the clocks were put forward, taken in the offset before the change
+                    MappedLocalTime::None => now
+                        .timezone()
+                        .from_utc_datetime(&(local - now.offset().fix())),
+                }
+            })
+            .find(|time| *time >= now)
+            .map_or(0, |time| (time - now).num_seconds() as u64)
+    }
+
+    pub fn duration_till_minute(&self, minute: u64) -> Duration {
+        Duration::from_secs(self.secs_till_minute(minute))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset};
+
+    use super::*;
+    use crate::types::time_zone::TzRule;
+
+    fn at(time: &str) -> GlobalInstant<FixedOffset> {
+        let datetime: DateTime<FixedOffset> = time.parse().unwrap();
+        GlobalInstant {
+            datetime,
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    #[test]
+    fn day_minute_uses_local_time() {
+        assert_eq!(at("2014-11-28T21:00:09+09:00").day_minute(), 21 * 60);
+        assert_eq!(at("2014-11-28T00:59:59-01:00").day_minute(), 59);
+    }
+
+    #[test]
+    fn secs_till_minute_wraps_midnight() {
+        let now = at("2014-11-28T21:00:00+00:00");
+
+        assert_eq!(now.secs_till_minute(22 * 60), 60 * 60);
+        assert_eq!(now.secs_till_minute(20 * 60), 23 * 60 * 60);
+        assert_eq!(now.secs_till_minute(21 * 60), 0);
+    }
+
+    #[test]
+    fn elapsed_time_moves_the_day_forward() {
+        let mut now = at("2014-11-28T23:59:00+00:00");
+        now.elapsed = Duration::from_secs(2 * 60);
+
+        assert_eq!(now.day_minute(), 1);
+        assert_eq!(now.secs_till_minute(2), 60);
+        assert_eq!(now.datetime(), at("2014-11-29T00:01:00+00:00").datetime);
+    }
+
+    fn in_zone(time: &str, elapsed: Duration) -> GlobalInstant<TzRule> {
+        let rule = TzRule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let datetime: DateTime<FixedOffset> = time.parse().unwrap();
+        GlobalInstant {
+            datetime: datetime.with_timezone(&rule),
+            elapsed,
+        }
+    }
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        // a day after noon in winter is 13:00 in summer time
+        let now = in_zone("2025-03-29T12:00:00+01:00", day);
+        assert_eq!(now.day_minute(), 13 * 60);
+        assert_eq!(now.datetime(), at("2025-03-30T13:00:00+02:00").datetime);
+
+        // the night of the change is an hour shorter, the one in autumn an hour longer
+        let midnight = in_zone("2025-03-30T00:00:00+01:00", Duration::from_secs(0));
+        assert_eq!(midnight.secs_till_minute(9 * 60), 8 * 60 * 60);
+        let midnight = in_zone("2025-10-26T00:00:00+02:00", Duration::from_secs(0));
+        assert_eq!(midnight.secs_till_minute(9 * 60), 10 * 60 * 60);
+        assert_eq!(midnight.secs_till_minute(24 * 60), 25 * 60 * 60);
+
+        // a time skipped in spring is taken in winter time, 03:30 summer time
+        let evening = in_zone("2025-03-29T23:00:00+01:00", Duration::from_secs(0));
+        assert_eq!(evening.secs_till_minute(2 * 60 + 30), 3 * 60 * 60 + 30 * 60);
+    }
+}
@@ -1,6 +1,14 @@
 mod color;
 pub use color::Color;
 
+pub mod clock;
+
 pub mod global_time;
 
+pub mod oklab;
+
 pub mod ranges;
+
+pub mod time_zone;
+
+pub mod sun;
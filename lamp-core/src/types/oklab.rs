@@ -0,0 +1,113 @@
+use super::Color;
+
+/// Color in the OKLab perceptual color space by Björn Ottosson.
+///
+/// Effect colors are treated as sRGB encoded, the brightness correction of the output
+/// stage plays the role of the sRGB transfer function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl Oklab {
+    pub fn interpolate(&self, other: Self, t: f32) -> Self {
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        Self {
+            l: lerp(self.l, other.l),
+            a: lerp(self.a, other.a),
+            b: lerp(self.b, other.b),
+        }
+    }
+}
+
+fn to_linear(c: u16) -> f32 {
+    let c = c as f32 / u16::MAX as f32;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        libm::powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+fn from_linear(c: f32) -> u16 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * libm::powf(c, 1.0 / 2.4) - 0.055
+    };
+    libm::roundf(c * u16::MAX as f32) as u16
+}
+
+impl From<Color> for Oklab {
+    fn from(color: Color) -> Self {
+        let [r, g, b] = color.rgb16().map(to_linear);
+
+        let l = libm::cbrtf(0.41222147 * r + 0.53633254 * g + 0.05144599 * b);
+        let m = libm::cbrtf(0.2119035 * r + 0.6806995 * g + 0.10739696 * b);
+        let s = libm::cbrtf(0.08830246 * r + 0.28171884 * g + 0.6299787 * b);
+
+        Self {
+            l: 0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            a: 1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            b: 0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        }
+    }
+}
+
+impl From<Oklab> for Color {
+    fn from(lab: Oklab) -> Self {
+        let l = lab.l + 0.39633778 * lab.a + 0.21580376 * lab.b;
+        let m = lab.l - 0.105561346 * lab.a - 0.06385417 * lab.b;
+        let s = lab.l - 0.08948418 * lab.a - 1.2914855 * lab.b;
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+
+        Color::new16(
+            from_linear(4.0767417 * l - 3.3077116 * m + 0.23096994 * s),
+            from_linear(-1.268438 * l + 2.6097574 * m - 0.34131938 * s),
+            from_linear(-0.0041960864 * l - 0.7034186 * m + 1.7076147 * s),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_colors() {
+        for color in [
+            Color::black(),
+            Color::new(255, 255, 255),
+            Color::new(255, 244, 200),
+            Color::new(0, 0, 80),
+            Color::new(12, 200, 3),
+        ] {
+            let back: Color = Oklab::from(color).into();
+            for (x, y) in back.rgb16().into_iter().zip(color.rgb16()) {
+                assert!(x.abs_diff(y) <= 16, "{color:?} came back as {back:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn white_has_no_chroma() {
+        let white = Oklab::from(Color::new(255, 255, 255));
+        assert!((white.l - 1.0).abs() < 1e-3);
+        assert!(white.a.abs() < 1e-3 && white.b.abs() < 1e-3);
+    }
+
+    #[test]
+    fn blends_without_darkening() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+
+        let rgb_mid = Oklab::from(red.interpolate(green, 1, 2));
+        let lab_mid = Oklab::from(red).interpolate(Oklab::from(green), 0.5);
+
+        assert!(lab_mid.l > rgb_mid.l);
+        assert!((lab_mid.l - (Oklab::from(red).l + Oklab::from(green).l) / 2.0).abs() < 1e-6);
+    }
+}
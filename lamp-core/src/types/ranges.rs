@@ -0,0 +1,187 @@
+use core::ops::{Index, IndexMut};
+
+use chrono::TimeZone;
+use embassy_time::Duration;
+
+use crate::types::global_time::GlobalInstant;
+
+#[derive(Debug)]
+pub struct RangesError;
+
+/// Boundaries of up to `N` ranges that cover a cycle, range `i` goes from value `i - 1`
+/// to value `i` and range 0 from the last value to the first one.
+///
+/// The values increase along the cycle, which may wrap around once,
+/// e.g. minutes of the day like `[1320, 1380, 180, 240]` cross midnight.
+#[derive(Clone)]
+pub struct OverlapRanges<T: Eq + Ord, const N: usize> {
+    ranges: [T; N],
+    // number of values in use, the rest of the array is padding
+    len: usize,
+}
+
+impl<T: Eq + Ord, const N: usize> Index<usize> for OverlapRanges<T, N> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.ranges[index % self.len]
+    }
+}
+
+impl<T: Eq + Ord, const N: usize> IndexMut<usize> for OverlapRanges<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.ranges[index % self.len]
+    }
+}
+
+impl<T: Eq + Ord, const N: usize> OverlapRanges<T, N> {
+    pub fn new(ranges: [T; N]) -> Result<Self, RangesError> {
+        Self::check_order(&ranges)?;
+        Ok(Self { ranges, len: N })
+    }
+
+    /// Ranges bounded by the first `values.len()` of the `N` values, at least one.
+    pub fn from_slice(values: &[T]) -> Result<Self, RangesError>
+    where
+        T: Copy + Default,
+    {
+        if values.is_empty() || values.len() > N {
+            Err(RangesError)?
+        }
+        Self::check_order(values)?;
+
+        let mut ranges = [T::default(); N];
+        ranges[..values.len()].copy_from_slice(values);
+        Ok(Self {
+            ranges,
+            len: values.len(),
+        })
+    }
+
+    // ensure values are well ordered, going around the cycle they only fall once
+    fn check_order(values: &[T]) -> Result<(), RangesError> {
+        let len = values.len();
+        let falls = (0..len)
+            .filter(|&i| values[i] >= values[(i + 1) % len])
+            .count();
+        match falls {
+            1 => Ok(()),
+            _ => Err(RangesError),
+        }
+    }
+
+    /// Number of ranges.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn which(&self, value: T) -> usize {
+        (0..self.len)
+            .find(|&i| {
+                let start = &self[i + self.len - 1];
+                let end = &self[i];
+                match start < end {
+                    true => start <= &value && &value < end,
+                    // the range wraps around
+                    false => start <= &value || &value < end,
+                }
+            })
+            .expect("because the array is well ordered")
+    }
+}
+
+/// Minutes in a day, the cycle of ranges of day minutes.
+pub const DAY_MINUTES: u64 = 24 * 60;
+
+impl<const N: usize> OverlapRanges<u64, N> {
+    /// Length of range `index` in minutes, counting across midnight.
+    pub fn minutes_in(&self, index: usize) -> u64 {
+        minutes_between(self[index + self.len - 1], self[index])
+    }
+
+    /// Minutes of range `index` passed at `minute` of the day, counting across midnight.
+    pub fn minutes_into(&self, index: usize, minute: u64) -> u64 {
+        minutes_between(self[index + self.len - 1], minute)
+    }
+
+    pub fn duration_till_next<Tz: TimeZone>(&self, now: GlobalInstant<Tz>) -> Duration {
+        let which = self.which(now.day_minute());
+        now.duration_till_minute(self[which])
+    }
+}
+
+fn minutes_between(from: u64, to: u64) -> u64 {
+    (to + DAY_MINUTES - from % DAY_MINUTES) % DAY_MINUTES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires_increasing_values() {
+        assert!(OverlapRanges::new([1, 2, 3, 4]).is_ok());
+        assert!(OverlapRanges::new([1, 2, 2, 4]).is_err());
+        assert!(OverlapRanges::new([4, 3, 2, 1]).is_err());
+        assert!(OverlapRanges::new([3, 4, 1, 2]).is_ok());
+        assert!(OverlapRanges::new([3, 4, 4, 2]).is_err());
+    }
+
+    #[test]
+    fn which_wraps_around() {
+        let ranges = OverlapRanges::new([540, 600, 1260, 1320]).unwrap();
+
+        assert_eq!(ranges.which(0), 0);
+        assert_eq!(ranges.which(539), 0);
+        assert_eq!(ranges.which(540), 1);
+        assert_eq!(ranges.which(599), 1);
+        assert_eq!(ranges.which(600), 2);
+        assert_eq!(ranges.which(1260), 3);
+        assert_eq!(ranges.which(1320), 0);
+        assert_eq!(ranges.which(1439), 0);
+    }
+
+    #[test]
+    fn which_crosses_midnight() {
+        // rising from 22:00, falling until 4:00
+        let ranges = OverlapRanges::new([1320, 1380, 180, 240]).unwrap();
+
+        assert_eq!(ranges.which(1319), 0);
+        assert_eq!(ranges.which(1320), 1);
+        assert_eq!(ranges.which(1439), 2);
+        assert_eq!(ranges.which(0), 2);
+        assert_eq!(ranges.which(200), 3);
+        assert_eq!(ranges.which(240), 0);
+
+        assert_eq!(ranges.minutes_in(2), 240);
+        assert_eq!(ranges.minutes_into(2, 30), 90);
+        assert_eq!(ranges.minutes_in(0), 1080);
+    }
+
+    #[test]
+    fn uses_part_of_the_values() {
+        let ranges = OverlapRanges::<u64, 8>::from_slice(&[360, 480, 1200]).unwrap();
+
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges.which(100), 0);
+        assert_eq!(ranges.which(400), 1);
+        assert_eq!(ranges.which(1300), 0);
+        assert_eq!(ranges[3], 360);
+        assert_eq!(ranges.minutes_in(0), 600);
+
+        assert!(OverlapRanges::<u64, 2>::from_slice(&[1, 2, 3]).is_err());
+        assert!(OverlapRanges::<u64, 2>::from_slice(&[]).is_err());
+    }
+
+    #[test]
+    fn index_wraps_around() {
+        let ranges = OverlapRanges::new([540, 600, 1260, 1320]).unwrap();
+
+        assert_eq!(ranges[4], 540);
+        assert_eq!(ranges[7], 1320);
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Altitude of the center of the sun at sunrise and sunset, with the refraction of the air
+/// and the radius of the sun, in degrees.
+pub const SUNRISE_ALTITUDE: f64 = -0.833;
+/// Altitude of the sun at the start of the civil dawn and the end of the civil dusk, in degrees.
+pub const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+
+// Julian date of 2000-01-01 12:00, the epoch of the sunrise equation
+const J2000: f64 = 2_451_545.0;
+// Julian date of the Unix epoch
+const UNIX_EPOCH: f64 = 2_440_587.5;
+const EARTH_TILT: f64 = 23.4397;
+
+/// Place on earth, in degrees north and east.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+/// When the sun passes an altitude on a day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Crossing {
+    /// The sun goes above the altitude at the first time and below it at the second one.
+    Between(DateTime<Utc>, DateTime<Utc>),
+    /// The sun stays above the altitude all day, e.g. in the polar summer.
+    Above,
+    /// The sun stays below the altitude all day.
+    Below,
+}
+
+impl Location {
+    /// Time at which the sun is highest on `date`, the solar noon.
+    pub fn noon(&self, date: NaiveDate) -> DateTime<Utc> {
+        to_datetime(self.sun(date).0)
+    }
+
+    /// When the sun passes `altitude` degrees on `date`, following the sunrise equation,
+    /// which is within a minute or two of the observed times away from the poles.
+    pub fn crossing(&self, date: NaiveDate, altitude: f64) -> Crossing {
+        let (transit, declination) = self.sun(date);
+        let latitude = (self.latitude as f64).to_radians();
+
+        let cos_hour_angle = (libm::sin(altitude.to_radians())
+            - libm::sin(latitude) * libm::sin(declination))
+            / (libm::cos(latitude) * libm::cos(declination));
+        if cos_hour_angle > 1.0 {
+            return Crossing::Below;
+        }
+        if cos_hour_angle < -1.0 {
+            return Crossing::Above;
+        }
+
+        // fraction of the day from the crossing to the solar noon
+        let half_day = libm::acos(cos_hour_angle).to_degrees() / 360.0;
+        Crossing::Between(
+            to_datetime(transit - half_day),
+            to_datetime(transit + half_day),
+        )
+    }
+
+    // Julian date of the solar noon on `date` and the declination of the sun then, in radians
+    fn sun(&self, date: NaiveDate) -> (f64, f64) {
+        let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).expect("because the date exists");
+        let mean_noon = (date - j2000).num_days() as f64 - self.longitude as f64 / 360.0;
+
+        let anomaly = (357.5291 + 0.98560028 * mean_noon).to_radians();
+        let center = 1.9148 * libm::sin(anomaly)
+            + 0.0200 * libm::sin(2.0 * anomaly)
+            + 0.0003 * libm::sin(3.0 * anomaly);
+        let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372).to_radians();
+
+        let transit = J2000 + mean_noon + 0.0053 * libm::sin(anomaly)
+            - 0.0069 * libm::sin(2.0 * ecliptic_longitude);
+        let declination =
+            libm::asin(libm::sin(ecliptic_longitude) * libm::sin(EARTH_TILT.to_radians()));
+        (transit, declination)
+    }
+}
+
+fn to_datetime(julian_date: f64) -> DateTime<Utc> {
+    let seconds = libm::round((julian_date - UNIX_EPOCH) * 86_400.0) as i64;
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BERLIN: Location = Location {
+        latitude: 52.52,
+        longitude: 13.405,
+    };
+
+    fn date(iso: &str) -> NaiveDate {
+        iso.parse().unwrap()
+    }
+
+    fn assert_near(time: DateTime<Utc>, expected: &str) {
+        let expected: DateTime<Utc> = expected.parse().unwrap();
+        let error = (time - expected).num_seconds();
+        assert!(
+            (-120..=120).contains(&error),
+            "{time} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn finds_sunrise_and_sunset() {
+        let Crossing::Between(sunrise, sunset) =
+            BERLIN.crossing(date("2025-06-21"), SUNRISE_ALTITUDE)
+        else {
+            panic!("the sun rises in Berlin");
+        };
+        assert_near(sunrise, "2025-06-21T02:43:00Z");
+        assert_near(sunset, "2025-06-21T19:33:00Z");
+        assert_near(BERLIN.noon(date("2025-06-21")), "2025-06-21T11:08:00Z");
+
+        let Crossing::Between(sunrise, sunset) =
+            BERLIN.crossing(date("2025-12-21"), SUNRISE_ALTITUDE)
+        else {
+            panic!("the sun rises in Berlin");
+        };
+        assert_near(sunrise, "2025-12-21T07:15:00Z");
+        assert_near(sunset, "2025-12-21T14:54:00Z");
+    }
+
+    #[test]
+    fn finds_civil_twilight() {
+        let Crossing::Between(dawn, dusk) =
+            BERLIN.crossing(date("2025-03-20"), CIVIL_TWILIGHT_ALTITUDE)
+        else {
+            panic!("the sun sets in Berlin");
+        };
+        assert_near(dawn, "2025-03-20T04:35:00Z");
+        assert_near(dusk, "2025-03-20T17:52:00Z");
+    }
+
+    #[test]
+    fn knows_polar_days_and_nights() {
+        let tromso = Location {
+            latitude: 69.65,
+            longitude: 18.96,
+        };
+
+        assert_eq!(
+            tromso.crossing(date("2025-06-21"), SUNRISE_ALTITUDE),
+            Crossing::Above
+        );
+        assert_eq!(
+            tromso.crossing(date("2025-12-21"), SUNRISE_ALTITUDE),
+            Crossing::Below
+        );
+        // there is still some light at noon
+        assert!(matches!(
+            tromso.crossing(date("2025-12-21"), CIVIL_TWILIGHT_ALTITUDE),
+            Crossing::Between(..)
+        ));
+    }
+}
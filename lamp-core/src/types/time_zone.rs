@@ -0,0 +1,440 @@
+use core::fmt;
+
+use chrono::{
+    Datelike, FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeDelta,
+    TimeZone, Weekday,
+};
+
+/// Time zone given by a POSIX TZ rule like `CET-1CEST,M3.5.0,M10.5.0/3`,
+/// local times follow its daylight saving changes.
+///
+/// Only the offsets and the changes are kept, not the names of the zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TzRule {
+    std: FixedOffset,
+    dst: Option<DstRule>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DstRule {
+    offset: FixedOffset,
+    // the start is given in local standard time, the end in local daylight saving time
+    start: Change,
+    end: Change,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Change {
+    date: ChangeDate,
+    // seconds after local midnight, may be negative or past the end of the day
+    time: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChangeDate {
+    /// `Jn`: day 1 to 365, February 29 is never counted
+    Julian(u16),
+    /// `n`: day 0 to 365, counting February 29
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` (5 is the last one) of month `m`
+    Month { month: u8, week: u8, weekday: u8 },
+}
+
+/// Offset of a [`TzRule`] at some time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TzOffset {
+    rule: TzRule,
+    offset: FixedOffset,
+}
+
+// rules that name a daylight saving time without saying when it applies follow the US
+const DEFAULT_START: Change = Change {
+    date: ChangeDate::Month {
+        month: 3,
+        week: 2,
+        weekday: 0,
+    },
+    time: 2 * 60 * 60,
+};
+const DEFAULT_END: Change = Change {
+    date: ChangeDate::Month {
+        month: 11,
+        week: 1,
+        weekday: 0,
+    },
+    time: 2 * 60 * 60,
+};
+
+impl TzRule {
+    /// Zone without daylight saving time.
+    pub fn fixed(offset: FixedOffset) -> Self {
+        Self {
+            std: offset,
+            dst: None,
+        }
+    }
+
+    /// Parses a POSIX TZ rule, `None` if it is not valid.
+    ///
+    /// Offsets may be given with minutes and seconds (`IST-5:30`), unusual names in angle brackets
+    /// (`<+0330>-3:30`) and change times outside of the day (`M3.5.0/-1`).
+    pub fn parse(rule: &str) -> Option<Self> {
+        let mut parser = Parser {
+            rest: rule.as_bytes(),
+        };
+
+        parser.name()?;
+        let std = parser.utc_offset()?;
+        if parser.rest.is_empty() {
+            return Some(Self::fixed(std));
+        }
+
+        parser.name()?;
+        let offset = match parser.rest.first() {
+            None | Some(b',') => FixedOffset::east_opt(std.local_minus_utc() + 60 * 60)?,
+            Some(_) => parser.utc_offset()?,
+        };
+        let (start, end) = match parser.eat(b',') {
+            true => {
+                let start = parser.change()?;
+                parser.eat(b',').then_some(())?;
+                (start, parser.change()?)
+            }
+            false => (DEFAULT_START, DEFAULT_END),
+        };
+
+        parser.rest.is_empty().then_some(Self {
+            std,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    /// UTC offset of local time at the UTC time `utc`.
+    pub fn offset_at(&self, utc: &NaiveDateTime) -> FixedOffset {
+        match self.dst {
+            Some(dst) if dst.is_active(self.std, utc) => dst.offset,
+            _ => self.std,
+        }
+    }
+
+    fn with_offset(&self, offset: FixedOffset) -> TzOffset {
+        TzOffset {
+            rule: *self,
+            offset,
+        }
+    }
+}
+
+impl DstRule {
+    fn is_active(&self, std: FixedOffset, utc: &NaiveDateTime) -> bool {
+        let year = (*utc + std).year();
+        let (Some(start), Some(end)) = (self.start.local(year), self.end.local(year)) else {
+            return false;
+        };
+        let (start, end) = (start - std, end - self.offset);
+
+        match start < end {
+            true => start <= *utc && *utc < end,
+            // southern hemisphere, daylight saving time spans the new year
+            false => !(end <= *utc && *utc < start),
+        }
+    }
+}
+
+impl Change {
+    // local time of the change in `year`
+    fn local(&self, year: i32) -> Option<NaiveDateTime> {
+        let date = match self.date {
+            ChangeDate::Julian(day) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                let ordinal = if leap && day >= 60 { day + 1 } else { day };
+                NaiveDate::from_yo_opt(year, ordinal.into())?
+            }
+            ChangeDate::Ordinal(day) => NaiveDate::from_yo_opt(year, u32::from(day) + 1)?,
+            ChangeDate::Month {
+                month,
+                week,
+                weekday,
+            } => {
+                const WEEKDAYS: [Weekday; 7] = [
+                    Weekday::Sun,
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                    Weekday::Sat,
+                ];
+                let weekday = WEEKDAYS[usize::from(weekday)];
+                let month = u32::from(month);
+                // week 5 is the last one, which may be the fourth
+                NaiveDate::from_weekday_of_month_opt(year, month, weekday, week)
+                    .or_else(|| NaiveDate::from_weekday_of_month_opt(year, month, weekday, 4))?
+            }
+        };
+        Some(date.and_time(NaiveTime::MIN) + TimeDelta::seconds(self.time.into()))
+    }
+}
+
+struct Parser<'r> {
+    rest: &'r [u8],
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, byte: u8) -> bool {
+        match self.rest.split_first() {
+            Some((first, rest)) if *first == byte => {
+                self.rest = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // at least three letters, or any name in angle brackets
+    fn name(&mut self) -> Option<()> {
+        let (len, skip) = match self.eat(b'<') {
+            true => {
+                let len = self.rest.iter().position(|&byte| byte == b'>')?;
+                (len, len + 1)
+            }
+            false => {
+                let len = self
+                    .rest
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_alphabetic())
+                    .count();
+                (len, len)
+            }
+        };
+        self.rest = &self.rest[skip..];
+        (len >= 3).then_some(())
+    }
+
+    fn number(&mut self, max: u16) -> Option<u16> {
+        let len = self
+            .rest
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if !(1..=3).contains(&len) {
+            return None;
+        }
+        let (digits, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        let number = digits
+            .iter()
+            .fold(0, |number, digit| number * 10 + u16::from(digit - b'0'));
+        (number <= max).then_some(number)
+    }
+
+    // [+-]hh[:mm[:ss]] in seconds
+    fn time(&mut self, max_hours: u16) -> Option<i32> {
+        let sign = match self.eat(b'-') {
+            true => -1,
+            false => {
+                self.eat(b'+');
+                1
+            }
+        };
+        let hours = i32::from(self.number(max_hours)?);
+        let minutes = match self.eat(b':') {
+            true => i32::from(self.number(59)?),
+            false => 0,
+        };
+        let seconds = match self.eat(b':') {
+            true => i32::from(self.number(59)?),
+            false => 0,
+        };
+        Some(sign * (hours * 60 * 60 + minutes * 60 + seconds))
+    }
+
+    // POSIX offsets are west of Greenwich, `CET-1` is an hour ahead of UTC
+    fn utc_offset(&mut self) -> Option<FixedOffset> {
+        FixedOffset::east_opt(-self.time(24)?)
+    }
+
+    fn change(&mut self) -> Option<Change> {
+        let date = if self.eat(b'J') {
+            ChangeDate::Julian(self.number(365).filter(|&day| day >= 1)?)
+        } else if self.eat(b'M') {
+            let month = self.number(12).filter(|&month| month >= 1)? as u8;
+            self.eat(b'.').then_some(())?;
+            let week = self.number(5).filter(|&week| week >= 1)? as u8;
+            self.eat(b'.').then_some(())?;
+            let weekday = self.number(6)? as u8;
+            ChangeDate::Month {
+                month,
+                week,
+                weekday,
+            }
+        } else {
+            ChangeDate::Ordinal(self.number(365)?)
+        };
+        let time = match self.eat(b'/') {
+            true => self.time(167)?,
+            false => 2 * 60 * 60,
+        };
+        Some(Change { date, time })
+    }
+}
+
+impl Offset for TzOffset {
+    fn fix(&self) -> FixedOffset {
+        self.offset
+    }
+}
+
+impl fmt::Display for TzOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.offset.fmt(f)
+    }
+}
+
+impl TimeZone for TzRule {
+    type Offset = TzOffset;
+
+    fn from_offset(offset: &TzOffset) -> Self {
+        offset.rule
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<TzOffset> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<TzOffset> {
+        let Some(dst) = self.dst else {
+            return MappedLocalTime::Single(self.with_offset(self.std));
+        };
+
+        // the larger offset comes first in UTC
+        let (first, second) = match dst.offset.local_minus_utc() > self.std.local_minus_utc() {
+            true => (dst.offset, self.std),
+            false => (self.std, dst.offset),
+        };
+        let fits = |offset: FixedOffset| self.offset_at(&(*local - offset)) == offset;
+        match (fits(first), fits(second)) {
+            (true, true) if first != second => {
+                MappedLocalTime::Ambiguous(self.with_offset(first), self.with_offset(second))
+            }
+            (true, _) => MappedLocalTime::Single(self.with_offset(first)),
+            (false, true) => MappedLocalTime::Single(self.with_offset(second)),
+            // skipped when the clocks are put forward
+            (false, false) => MappedLocalTime::None,
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> TzOffset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> TzOffset {
+        self.with_offset(self.offset_at(utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+
+    const CET: &str = "CET-1CEST,M3.5.0,M10.5.0/3";
+
+    fn offset_at(rule: &str, utc: &str) -> i32 {
+        let utc: DateTime<Utc> = utc.parse().unwrap();
+        TzRule::parse(rule)
+            .unwrap()
+            .offset_at(&utc.naive_utc())
+            .local_minus_utc()
+    }
+
+    fn local(time: &str) -> NaiveDateTime {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn follows_daylight_saving_changes() {
+        assert_eq!(offset_at(CET, "2025-01-15T12:00:00Z"), 3600);
+        assert_eq!(offset_at(CET, "2025-07-15T12:00:00Z"), 7200);
+
+        // 02:00 CET on the last Sunday of March, 03:00 CEST on the last Sunday of October
+        assert_eq!(offset_at(CET, "2025-03-30T00:59:59Z"), 3600);
+        assert_eq!(offset_at(CET, "2025-03-30T01:00:00Z"), 7200);
+        assert_eq!(offset_at(CET, "2025-10-26T00:59:59Z"), 7200);
+        assert_eq!(offset_at(CET, "2025-10-26T01:00:00Z"), 3600);
+    }
+
+    #[test]
+    fn spans_the_new_year_in_the_south() {
+        let sydney = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+
+        assert_eq!(offset_at(sydney, "2025-01-15T12:00:00Z"), 11 * 3600);
+        assert_eq!(offset_at(sydney, "2025-07-15T12:00:00Z"), 10 * 3600);
+        assert_eq!(offset_at(sydney, "2025-12-31T23:00:00Z"), 11 * 3600);
+    }
+
+    #[test]
+    fn parses_other_forms() {
+        assert_eq!(offset_at("UTC0", "2025-07-15T12:00:00Z"), 0);
+        assert_eq!(
+            offset_at("<+0330>-3:30", "2025-07-15T12:00:00Z"),
+            3 * 3600 + 1800
+        );
+        // the US rules when none are given, an hour ahead of standard time
+        assert_eq!(offset_at("EST5EDT", "2025-07-15T12:00:00Z"), -4 * 3600);
+        assert_eq!(offset_at("EST5EDT", "2025-11-15T12:00:00Z"), -5 * 3600);
+        assert_eq!(
+            offset_at("<-03>3<-02>,M3.5.0/-2,M10.5.0/-1", "2025-07-15T12:00:00Z"),
+            -2 * 3600
+        );
+        // day 60 is March 1 with and without a leap day
+        assert_eq!(
+            offset_at("AAA0BBB,J60/0,J300/0", "2024-03-01T00:00:00Z"),
+            3600
+        );
+        assert_eq!(offset_at("AAA0BBB,J60/0,J300/0", "2024-02-29T23:59:59Z"), 0);
+        assert_eq!(
+            offset_at("AAA0BBB,59/0,300/0", "2024-02-29T00:00:00Z"),
+            3600
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "",
+            "CET",
+            "CE-1",
+            "CET-25",
+            "CET-1CEST,M13.5.0,M10.5.0",
+            "CET-1CEST,M3.5.0",
+            "CET-1CEST,M3.5.0,M10.5.0/3 ",
+            "<CET-1",
+        ] {
+            assert_eq!(TzRule::parse(rule), None, "{rule}");
+        }
+    }
+
+    #[test]
+    fn maps_local_times() {
+        let cet = TzRule::parse(CET).unwrap();
+
+        let summer = cet
+            .from_local_datetime(&local("2025-07-15T12:00:00"))
+            .unwrap();
+        assert_eq!(summer.offset().fix().local_minus_utc(), 7200);
+
+        // skipped in spring, twice in autumn
+        assert_eq!(
+            cet.from_local_datetime(&local("2025-03-30T02:30:00")),
+            MappedLocalTime::None
+        );
+        let MappedLocalTime::Ambiguous(first, second) =
+            cet.from_local_datetime(&local("2025-10-26T02:30:00"))
+        else {
+            panic!("expected two times");
+        };
+        assert_eq!(second - first, TimeDelta::hours(1));
+    }
+}
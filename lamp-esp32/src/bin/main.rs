@@ -3,36 +3,82 @@
 #![feature(type_alias_impl_trait)]
 
 use lamp_esp32::{
+    clock::WallClock,
+    flash::RomFlash,
     leds::{
-        controller::LedController,
-        runner::{LedSignal, run_leds},
+        AnyDriver, StripOutput, fits_board,
+        pwm::{PwmConfig, PwmDriver},
+        rmt::{RmtChannel, RmtDriver},
+        runner::{StripCommand, StripHandle, run_leds, targeted},
+        spi::SpiDriver,
     },
     server::{
         Server,
+        sntp::sync_time,
         wifi::{connection, net_task},
     },
 };
 
+use lamp_core::{
+    config::{DeviceConfig, PowerOn, StripConfig, Timings},
+    output::{BrightnessCurve, ColorCorrection, StripLayout},
+    runner::Segment,
+    settings::{LastRequest, LocalTimeZone, SettingsStore, WifiCredentials},
+};
+
 use embassy_executor::Spawner;
 use embassy_net::StackResources;
+use embassy_sync::blocking_mutex::Mutex;
 use esp_hal::clock::CpuClock;
 use esp_hal::{
     gpio::Pin,
+    ledc::timer::config::Duty,
     rng::Rng,
+    time::Rate,
     timer::{systimer::SystemTimer, timg::TimerGroup},
 };
 use static_cell::make_static;
 
 extern crate alloc;
 
+use alloc::vec::Vec;
+use core::{cell::RefCell, ops::Range};
+
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
     loop {}
 }
 
+// network joined until other credentials are saved with POST /wifi
 const SSID: &str = env!("SSID");
 const PASSWORD: &str = env!("PASSWORD");
+// NUM_LEDS, the strip pins, SECOND_STRIP, BRIGHTNESS_CURVE and POWER_ON are the configuration
+// until another one is saved with POST /config
 const NUM_LEDS: usize = 12;
+// use StripLayout::SK6812_RGBW for lamps with RGBW strips
+const STRIP_LAYOUT: StripLayout = StripLayout::WS2812;
+// StripOutput::Rmt drives the data line on GPIO3,
+// StripOutput::Spi drives APA102 and SK9822 strips with data on GPIO3 and clock on GPIO4,
+// use StripLayout::APA102 with them,
+// StripOutput::Pwm(ANALOG_PWM) drives MOSFETs of a plain RGB strip on GPIO3, GPIO4 and GPIO6
+// (red, green, blue), NUM_LEDS and STRIP_LAYOUT do not apply then
+const STRIP_OUTPUT: StripOutput = StripOutput::Rmt;
+#[allow(dead_code)]
+const ANALOG_PWM: PwmConfig = PwmConfig {
+    frequency: Rate::from_khz(4),
+    resolution: Duty::Duty12Bit,
+};
+// length and layout of a second strip on GPIO5 and the other RMT channel,
+// e.g. Some((12, StripLayout::WS2812)), None when the lamp has a single strip
+const SECOND_STRIP: Option<(usize, StripLayout)> = None;
+// named LED ranges of the first strip that run their own effects,
+// e.g. &[("top", 0..8), ("back", 8..12)], the whole strip is one segment when empty
+const SEGMENTS: &[(&str, Range<usize>)] = &[];
+// BrightnessCurve::Gamma(2.2) or BrightnessCurve::Linear suit some strips better
+const BRIGHTNESS_CURVE: BrightnessCurve = BrightnessCurve::Cie;
+// PowerOn::Restore continues the last effect, PowerOn::Color(..) fades to a fixed color,
+// PowerOn::Off keeps the strips dark until the first request
+const POWER_ON: PowerOn = PowerOn::Restore;
 
 #[esp_hal_embassy::main]
 async fn main(spawner: Spawner) {
@@ -48,6 +94,17 @@ async fn main(spawner: Spawner) {
 
     let timer1 = TimerGroup::new(peripherals.TIMG0);
 
+    // the lamp still runs on the defaults when the flash cannot be read
+    let mut settings = SettingsStore::new(RomFlash::settings()).ok();
+    let credentials = settings
+        .as_mut()
+        .and_then(|settings| settings.load::<WifiCredentials>().ok().flatten())
+        .unwrap_or_else(|| WifiCredentials {
+            ssid: SSID.into(),
+            password: PASSWORD.into(),
+        });
+    let credentials = make_static!(credentials);
+
     //WiFi setup
     let esp_wifi_controller =
         make_static!(esp_wifi::init(timer1.timer0, rng, peripherals.RADIO_CLK,).unwrap());
@@ -63,23 +120,159 @@ async fn main(spawner: Spawner) {
     let (stack, runner) = embassy_net::new(
         wifi_interface,
         net_config,
-        make_static!(StackResources::<3>::new()),
+        make_static!(StackResources::<5>::new()),
         seed,
     );
 
     spawner
-        .spawn(connection(wifi_controller, SSID, PASSWORD))
+        .spawn(connection(
+            wifi_controller,
+            &credentials.ssid,
+            &credentials.password,
+        ))
         .ok();
     spawner.spawn(net_task(runner)).ok();
 
-    let led_signal = make_static!(LedSignal::new());
+    let segments = SEGMENTS
+        .iter()
+        .map(|(name, leds)| Segment {
+            name: (*name).into(),
+            leds: leds.clone(),
+        })
+        .collect();
+    let strips = make_static!([StripHandle::new(segments), StripHandle::new(Vec::new())]);
 
+    // a saved configuration the board cannot drive, e.g. one of a build with other pins, is ignored
+    let saved_config = settings
+        .as_mut()
+        .and_then(|settings| settings.load::<DeviceConfig>().ok().flatten())
+        .filter(|config| fits_board(config, strips.len()));
+    let mut device_config = saved_config.unwrap_or_else(|| {
+        let mut device_config = DeviceConfig {
+            strips: alloc::vec![StripConfig {
+                num_leds: NUM_LEDS,
+                pin: StripOutput::PIN,
+                timings: Timings::WS2813,
+            }],
+            brightness_curve: BRIGHTNESS_CURVE,
+            power_on: POWER_ON,
+        };
+        if let Some((num_leds, _)) = SECOND_STRIP {
+            device_config.strips.push(StripConfig {
+                num_leds,
+                pin: 5,
+                timings: Timings::WS2813,
+            });
+        }
+        device_config
+    });
+    // a configuration saved with an RMT strip may have moved it, the other outputs cannot
+    if !matches!(STRIP_OUTPUT, StripOutput::Rmt) {
+        device_config.strips[0].pin = StripOutput::PIN;
+    }
+
+    let [first_channel, second_channel] = RmtChannel::channels(peripherals.RMT).unwrap();
     let strip_pin = peripherals.GPIO3.degrade();
-    let controller = LedController::new(strip_pin, peripherals.RMT, NUM_LEDS).unwrap();
+    let driver: AnyDriver = match STRIP_OUTPUT {
+        // SAFETY: the strip pins are not used elsewhere
+        StripOutput::Rmt => {
+            unsafe { RmtDriver::new(first_channel, &device_config.strips[0], STRIP_LAYOUT) }.into()
+        }
+        StripOutput::Spi => {
+            let clock_pin = peripherals.GPIO4.degrade();
+            SpiDriver::new(
+                peripherals.SPI2,
+                peripherals.DMA_CH0,
+                clock_pin,
+                strip_pin,
+                device_config.strips[0].num_leds,
+                STRIP_LAYOUT,
+            )
+            .unwrap()
+            .into()
+        }
+        StripOutput::Pwm(config) => {
+            let pins = [
+                strip_pin,
+                peripherals.GPIO4.degrade(),
+                peripherals.GPIO6.degrade(),
+            ];
+            PwmDriver::new(peripherals.LEDC, pins, config)
+                .unwrap()
+                .into()
+        }
+    };
+
+    // the clock stays unset until a request or the NTP server tells the time,
+    // restored cycles wait for it in the dark
+    let wall_clock = make_static!(WallClock::new());
+    let zone = settings
+        .as_mut()
+        .and_then(|settings| settings.load::<LocalTimeZone>().ok().flatten());
+    if let Some(zone) = zone {
+        wall_clock.set_zone(zone);
+    }
+
+    let correction = ColorCorrection::new(device_config.brightness_curve);
+
+    spawner
+        .spawn(run_leds(
+            Some(driver),
+            None,
+            STRIP_LAYOUT,
+            correction.clone(),
+            &strips[0],
+            wall_clock,
+        ))
+        .ok();
+
+    // the task of the second strip also runs without one, to set it up once configured
+    let second_layout = SECOND_STRIP.map_or(STRIP_LAYOUT, |(_, layout)| layout);
+    let (second_driver, spare_channel) = match device_config.strips.get(1) {
+        // SAFETY: the pin of the second strip is not used elsewhere
+        Some(config) => {
+            let driver = unsafe { RmtDriver::new(second_channel, config, second_layout) };
+            (Some(driver.into()), None)
+        }
+        None => (None, Some(second_channel)),
+    };
+    spawner
+        .spawn(run_leds(
+            second_driver,
+            spare_channel,
+            second_layout,
+            correction,
+            &strips[1],
+            wall_clock,
+        ))
+        .ok();
 
-    spawner.spawn(run_leds(controller, led_signal)).ok();
+    let last_request = settings
+        .as_mut()
+        .and_then(|settings| settings.load::<LastRequest>().ok().flatten());
+    let power_on = device_config.power_on.request(last_request);
+    if let Some(LastRequest { target, request }) = power_on {
+        let num_strips = device_config.strips.len();
+        for strip in targeted(&strips[..num_strips], &target) {
+            let command = StripCommand::Request(target.segment.clone(), request.clone());
+            strip.commands.send(command).await;
+        }
+    }
 
-    let mut server = Server::<4096, 1024>::new(stack, led_signal);
+    let settings = make_static!(Mutex::new(RefCell::new(settings)));
+    spawner
+        .spawn(sync_time(stack, wall_clock, &strips[..]))
+        .ok();
+
+    let device_config = make_static!(Mutex::new(RefCell::new(device_config)));
+    let mut server = Server::<4096, 1024>::new(
+        stack,
+        &strips[..],
+        STRIP_OUTPUT,
+        device_config,
+        settings,
+        wall_clock,
+    );
 
     server.run().await;
 }
@@ -0,0 +1,72 @@
+use core::cell::RefCell;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::Instant;
+use lamp_core::{
+    settings::LocalTimeZone,
+    types::{
+        clock::{Clock, WallTime},
+        global_time::GlobalInstant,
+        time_zone::TzRule,
+    },
+};
+
+/// Clock of the lamp shared by all effects: the chip time as kept by the embassy time driver,
+/// and the wall time, known once a request or an NTP server told it,
+/// in the local time of the time zone of the lamp.
+pub struct WallClock {
+    time: Mutex<CriticalSectionRawMutex, RefCell<WallTime>>,
+    zone: Mutex<CriticalSectionRawMutex, RefCell<Option<LocalTimeZone>>>,
+}
+
+impl WallClock {
+    pub const fn new() -> Self {
+        Self {
+            time: Mutex::new(RefCell::new(WallTime::new())),
+            zone: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn set(&self, datetime: DateTime<FixedOffset>) {
+        self.time.lock(|cell| cell.borrow_mut().set(datetime, self));
+    }
+
+    /// Sets the time from a UTC one, keeping the local time of the lamp in the zone it was in.
+    pub fn set_utc(&self, utc: DateTime<Utc>) {
+        self.time.lock(|cell| cell.borrow_mut().set_utc(utc, self));
+    }
+
+    /// Makes local time follow `zone`, an empty one keeps the current UTC offset.
+    pub fn set_zone(&self, zone: LocalTimeZone) {
+        let rule = zone.rule();
+        self.time
+            .lock(|cell| cell.borrow_mut().set_zone(rule, self));
+        self.zone
+            .lock(|cell| cell.replace(rule.is_some().then_some(zone)));
+    }
+
+    pub fn zone(&self) -> Option<LocalTimeZone> {
+        self.zone.lock(|cell| cell.borrow().clone())
+    }
+
+    pub fn now(&self) -> Option<DateTime<FixedOffset>> {
+        self.wall_time().map(|now| now.datetime().fixed_offset())
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> Option<GlobalInstant<TzRule>> {
+        self.time.lock(|cell| cell.borrow().now(self))
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
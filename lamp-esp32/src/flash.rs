@@ -0,0 +1,199 @@
+use core::{cell::RefCell, future::Future};
+
+use embassy_sync::{
+    blocking_mutex::{
+        Mutex,
+        raw::{CriticalSectionRawMutex, NoopRawMutex},
+    },
+    mutex::Mutex as AsyncMutex,
+    rwlock::RwLock,
+};
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash, check_erase, check_read, check_write,
+};
+use esp_hal::ram;
+use lamp_core::settings::SettingsStore;
+
+// The nvs partition of the default partition table, which nothing else on the lamp uses
+const SETTINGS_OFFSET: u32 = 0x9000;
+const SETTINGS_SIZE: u32 = 0x6000;
+
+const SECTOR_SIZE: u32 = 4096;
+
+// Words copied through the stack per ROM call
+const CHUNK_WORDS: usize = 16;
+
+unsafe extern "C" {
+    fn esp_rom_spiflash_read(src_addr: u32, data: *mut u32, len: u32) -> i32;
+    fn esp_rom_spiflash_write(dest_addr: u32, data: *const u32, len: u32) -> i32;
+    fn esp_rom_spiflash_erase_sector(sector_number: u32) -> i32;
+    fn esp_rom_spiflash_unlock() -> i32;
+    fn Cache_Suspend_ICache() -> u32;
+    fn Cache_Resume_ICache(autoload: u32);
+}
+
+/// Settings store of the lamp, `None` when the flash could not be read.
+///
+/// Only the tasks of the main executor save settings, so the lock itself leaves interrupts on.
+/// Saves go through [`FLASH_GATE`], see [`RomFlash`].
+pub type SharedSettings = Mutex<NoopRawMutex, RefCell<Option<SettingsStore<RomFlash>>>>;
+
+/// Keeps accesses to the flash and RMT frames apart, see [`RomFlash`].
+pub static FLASH_GATE: FlashGate = FlashGate::new();
+
+/// Lets RMT frames share the time with flash accesses, which wait for the frames in flight.
+pub struct FlashGate {
+    // held by an access waiting for the frames, so no new ones start meanwhile
+    turnstile: AsyncMutex<CriticalSectionRawMutex, ()>,
+    frames: RwLock<CriticalSectionRawMutex, ()>,
+}
+
+impl FlashGate {
+    const fn new() -> Self {
+        Self {
+            turnstile: AsyncMutex::new(()),
+            frames: RwLock::new(()),
+        }
+    }
+
+    /// Sends an RMT frame once no flash access is running or waiting.
+    pub async fn frame<R>(&self, frame: impl Future<Output = R>) -> R {
+        drop(self.turnstile.lock().await);
+        let _frames = self.frames.read().await;
+        frame.await
+    }
+
+    /// Accesses the flash once the RMT frames in flight are done.
+    pub async fn access<R>(&self, access: impl FnOnce() -> R) -> R {
+        let _turnstile = self.turnstile.lock().await;
+        let _frames = self.frames.write().await;
+        access()
+    }
+}
+
+/// Region of the SPI flash the program runs from, accessed through the ROM routines.
+///
+/// The instruction cache is suspended while the flash is busy, so every ROM call runs
+/// from RAM within a critical section. Interrupts stay masked for the whole call,
+/// up to tens of milliseconds for erasing a sector, which stalls the WiFi meanwhile and
+/// would leave the RMT channels without refills, so accesses go through [`FLASH_GATE`]
+/// once the LED tasks run.
+pub struct RomFlash {
+    offset: u32,
+    size: u32,
+}
+
+impl RomFlash {
+    /// The partition the settings are kept in.
+    pub fn settings() -> Self {
+        Self {
+            offset: SETTINGS_OFFSET,
+            size: SETTINGS_SIZE,
+        }
+    }
+}
+
+impl ErrorType for RomFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RomFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+
+        // the ROM reads whole words, the bytes before `offset` in the first one are skipped
+        let mut address = self.offset + offset - offset % 4;
+        let mut skip = (offset % 4) as usize;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let mut words = [0u32; CHUNK_WORDS];
+            let result = critical_section::with(|_| unsafe { read_words(address, &mut words) });
+            if result != 0 {
+                Err(NorFlashErrorKind::Other)?
+            }
+
+            let chunk: [u8; CHUNK_WORDS * 4] = unsafe { core::mem::transmute(words) };
+            let len = (chunk.len() - skip).min(bytes.len());
+            bytes[..len].copy_from_slice(&chunk[skip..skip + len]);
+            bytes = &mut bytes[len..];
+            address += chunk.len() as u32;
+            skip = 0;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.size as usize
+    }
+}
+
+impl NorFlash for RomFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        for address in (self.offset + from..self.offset + to).step_by(Self::ERASE_SIZE) {
+            let result = critical_section::with(|_| unsafe { erase_sector(address / SECTOR_SIZE) });
+            if result != 0 {
+                Err(NorFlashErrorKind::Other)?
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+
+        let mut address = self.offset + offset;
+        for chunk in bytes.chunks(CHUNK_WORDS * 4) {
+            // the ROM takes word aligned data
+            let mut words = [0u32; CHUNK_WORDS];
+            let (chunk_words, _) = chunk.as_chunks::<4>();
+            for (word, bytes) in words.iter_mut().zip(chunk_words) {
+                *word = u32::from_le_bytes(*bytes);
+            }
+            let words = &words[..chunk.len() / 4];
+            let result = critical_section::with(|_| unsafe { write_words(address, words) });
+            if result != 0 {
+                Err(NorFlashErrorKind::Other)?
+            }
+            address += chunk.len() as u32;
+        }
+        Ok(())
+    }
+}
+
+#[ram]
+unsafe fn read_words(address: u32, words: &mut [u32; CHUNK_WORDS]) -> i32 {
+    unsafe {
+        let autoload = Cache_Suspend_ICache();
+        let result = esp_rom_spiflash_read(address, words.as_mut_ptr(), CHUNK_WORDS as u32 * 4);
+        Cache_Resume_ICache(autoload);
+        result
+    }
+}
+
+#[ram]
+unsafe fn write_words(address: u32, words: &[u32]) -> i32 {
+    unsafe {
+        let autoload = Cache_Suspend_ICache();
+        esp_rom_spiflash_unlock();
+        let result = esp_rom_spiflash_write(address, words.as_ptr(), words.len() as u32 * 4);
+        Cache_Resume_ICache(autoload);
+        result
+    }
+}
+
+#[ram]
+unsafe fn erase_sector(sector: u32) -> i32 {
+    unsafe {
+        let autoload = Cache_Suspend_ICache();
+        esp_rom_spiflash_unlock();
+        let result = esp_rom_spiflash_erase_sector(sector);
+        Cache_Resume_ICache(autoload);
+        result
+    }
+}
@@ -1,8 +0,0 @@
-mod effect;
-pub use effect::{Effect, EffectEnum, EffectStatus};
-
-mod move_to;
-pub use move_to::MoveTo;
-
-mod daylight_cycle;
-pub use daylight_cycle::DaylightCycle;
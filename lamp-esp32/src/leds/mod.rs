@@ -1,3 +1,162 @@
-pub mod controller;
-pub mod effects;
+pub mod pwm;
+pub mod rmt;
 pub mod runner;
+pub mod spi;
+
+use lamp_core::{
+    config::{DeviceConfig, StripConfig},
+    output::{LedDriver, Pixel, StripLayout},
+    types::Color,
+};
+
+use self::{
+    pwm::PwmDriver,
+    rmt::{RmtChannel, RmtDriver, RmtError},
+    spi::SpiDriver,
+};
+
+/// How the first strip of the lamp is connected.
+#[derive(Clone, Copy)]
+pub enum StripOutput {
+    /// WS281x or SK6812 data line.
+    Rmt,
+    /// APA102 or SK9822 data and clock lines.
+    Spi,
+    /// Plain RGB strip switched by MOSFETs, the strip shows a single color.
+    Pwm(pwm::PwmConfig),
+}
+
+impl StripOutput {
+    /// Data pin of the first strip, the SPI and PWM outputs stay on it.
+    pub const PIN: u8 = 3;
+
+    /// Whether the first strip can change from `current` to `new` without reflashing.
+    /// RMT strips take any configuration, SPI strips only a new length and PWM outputs none.
+    pub fn applies(&self, current: &StripConfig, new: &StripConfig) -> bool {
+        match self {
+            StripOutput::Rmt => true,
+            StripOutput::Spi => new.pin == current.pin && new.timings == current.timings,
+            StripOutput::Pwm(_) => new == current,
+        }
+    }
+}
+
+/// GPIOs a strip may be moved to at runtime.
+/// The flash and USB pins are left out, as are GPIO4 and GPIO6 used by the SPI and PWM outputs,
+/// the strapping pins GPIO2, GPIO8 and GPIO9 and the console pins GPIO20 and GPIO21.
+pub const STRIP_PINS: &[u8] = &[0, 1, 3, 5, 7, 10];
+
+/// Whether every strip of `config` gets one of the `num_tasks` LED tasks and a pin that is safe to drive.
+pub fn fits_board(config: &DeviceConfig, num_tasks: usize) -> bool {
+    config.strips.len() <= num_tasks
+        && config
+            .strips
+            .iter()
+            .all(|strip| STRIP_PINS.contains(&strip.pin))
+}
+
+/// One of the LED drivers of the board, the LED task is not generic over them.
+pub enum AnyDriver {
+    Rmt(RmtDriver),
+    Spi(SpiDriver),
+    Pwm(PwmDriver),
+}
+
+#[derive(Debug)]
+pub enum DriverError {
+    Rmt(RmtError),
+    Spi(esp_hal::spi::Error),
+}
+
+impl AnyDriver {
+    /// Applies the runtime configuration of the strip, `pins` are the data pins of all strips.
+    /// RMT strips move to the new pin and timings, SPI strips only take the new length,
+    /// PWM outputs stay as they are, see [`StripOutput::applies`].
+    ///
+    /// # Safety
+    ///
+    /// See [`RmtDriver::reconfigure`].
+    pub unsafe fn reconfigure(&mut self, config: &StripConfig, pins: &[u8]) {
+        match self {
+            AnyDriver::Rmt(driver) => unsafe { driver.reconfigure(config, pins) },
+            AnyDriver::Spi(driver) => driver.set_num_leds(config.num_leds),
+            AnyDriver::Pwm(_) => {}
+        }
+    }
+
+    /// Stops driving the strip and gives back its RMT channel, see [`RmtDriver::release`].
+    pub fn release(self, pins: &[u8]) -> Option<RmtChannel> {
+        match self {
+            AnyDriver::Rmt(driver) => Some(driver.release(pins)),
+            _ => None,
+        }
+    }
+}
+
+impl From<RmtDriver> for AnyDriver {
+    fn from(driver: RmtDriver) -> Self {
+        AnyDriver::Rmt(driver)
+    }
+}
+
+impl From<SpiDriver> for AnyDriver {
+    fn from(driver: SpiDriver) -> Self {
+        AnyDriver::Spi(driver)
+    }
+}
+
+impl From<PwmDriver> for AnyDriver {
+    fn from(driver: PwmDriver) -> Self {
+        AnyDriver::Pwm(driver)
+    }
+}
+
+impl LedDriver for AnyDriver {
+    type Error = DriverError;
+
+    fn num_leds(&self) -> usize {
+        match self {
+            AnyDriver::Rmt(driver) => driver.num_leds(),
+            AnyDriver::Spi(driver) => driver.num_leds(),
+            AnyDriver::Pwm(driver) => driver.num_leds(),
+        }
+    }
+
+    fn layout(&self) -> StripLayout {
+        match self {
+            AnyDriver::Rmt(driver) => driver.layout(),
+            AnyDriver::Spi(driver) => driver.layout(),
+            AnyDriver::Pwm(driver) => driver.layout(),
+        }
+    }
+
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), DriverError> {
+        match self {
+            AnyDriver::Rmt(driver) => driver.send_frame(frame).await.map_err(DriverError::Rmt),
+            AnyDriver::Spi(driver) => driver.send_frame(frame).await.map_err(DriverError::Spi),
+            AnyDriver::Pwm(driver) => driver
+                .send_frame(frame)
+                .await
+                .map_err(|never| match never {}),
+        }
+    }
+
+    fn takes_colors(&self) -> bool {
+        match self {
+            AnyDriver::Rmt(driver) => driver.takes_colors(),
+            AnyDriver::Spi(driver) => driver.takes_colors(),
+            AnyDriver::Pwm(driver) => driver.takes_colors(),
+        }
+    }
+
+    async fn send_colors(&mut self, frame: &[Color]) -> Result<(), DriverError> {
+        match self {
+            AnyDriver::Rmt(driver) => driver.send_colors(frame).await.map_err(DriverError::Rmt),
+            AnyDriver::Spi(driver) => driver.send_colors(frame).await.map_err(DriverError::Spi),
+            AnyDriver::Pwm(driver) => driver
+                .send_colors(frame)
+                .await
+                .map_err(|never| match never {}),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use core::convert::Infallible;
+
+use esp_hal::{
+    gpio::AnyPin,
+    ledc::{
+        LSGlobalClkSource, Ledc, LowSpeed,
+        channel::{self, ChannelHW, ChannelIFace},
+        timer::{self, Timer, TimerIFace, config::Duty},
+    },
+    peripheral::Peripheral,
+    peripherals::LEDC,
+    time::Rate,
+};
+use static_cell::StaticCell;
+
+use lamp_core::{
+    output::{ChannelOrder, LedDriver, Pixel, StripLayout},
+    types::Color,
+};
+
+// the channels keep a reference to the timer for as long as they live
+static TIMER: StaticCell<Timer<'static, LowSpeed>> = StaticCell::new();
+
+/// PWM signal of the color channels.
+#[derive(Clone, Copy)]
+pub struct PwmConfig {
+    pub frequency: Rate,
+    /// Frequency times the number of duty steps has to stay below the 80 MHz clock.
+    pub resolution: Duty,
+}
+
+#[derive(Debug)]
+pub enum PwmError {
+    Timer(timer::Error),
+    Channel(channel::Error),
+}
+
+/// Driver for plain RGB strips switched by MOSFETs, the strip shows a single color.
+pub struct PwmDriver {
+    // red, green and blue
+    channels: [channel::Channel<'static, LowSpeed>; 3],
+    full_duty: u32,
+}
+
+impl PwmDriver {
+    pub fn new(
+        peripheral: impl Peripheral<P = LEDC> + 'static,
+        [red, green, blue]: [AnyPin; 3],
+        config: PwmConfig,
+    ) -> Result<Self, PwmError> {
+        let mut ledc = Ledc::new(peripheral);
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+        let timer = TIMER.init(ledc.timer(timer::Number::Timer0));
+        timer
+            .configure(timer::config::Config {
+                duty: config.resolution,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: config.frequency,
+            })
+            .map_err(PwmError::Timer)?;
+        let timer: &'static Timer<'static, LowSpeed> = timer;
+
+        let mut channels = [
+            ledc.channel(channel::Number::Channel0, red),
+            ledc.channel(channel::Number::Channel1, green),
+            ledc.channel(channel::Number::Channel2, blue),
+        ];
+        for channel in &mut channels {
+            channel
+                .configure(channel::config::Config {
+                    timer,
+                    duty_pct: 0,
+                    pin_config: channel::config::PinConfig::PushPull,
+                })
+                .map_err(PwmError::Channel)?;
+        }
+
+        Ok(Self {
+            channels,
+            // the duty register counts up to 2^resolution for a signal that is always on
+            full_duty: 1 << config.resolution as u32,
+        })
+    }
+}
+
+impl LedDriver for PwmDriver {
+    type Error = Infallible;
+
+    fn num_leds(&self) -> usize {
+        1
+    }
+
+    fn layout(&self) -> StripLayout {
+        StripLayout {
+            order: ChannelOrder::Rgb,
+            white: false,
+        }
+    }
+
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), Infallible> {
+        let Some(pixel) = frame.first() else {
+            return Ok(());
+        };
+
+        for (channel, value) in self.channels.iter().zip([pixel.r, pixel.g, pixel.b]) {
+            channel.set_duty_hw(value as u32 * self.full_duty / 255);
+        }
+        Ok(())
+    }
+
+    // the duty steps are finer than 8 bits, so the corrected colors are not dithered
+    fn takes_colors(&self) -> bool {
+        true
+    }
+
+    async fn send_colors(&mut self, frame: &[Color]) -> Result<(), Infallible> {
+        let Some(color) = frame.first() else {
+            return Ok(());
+        };
+
+        for (channel, value) in self.channels.iter().zip(color.rgb16()) {
+            let duty = value as u64 * self.full_duty as u64 / u16::MAX as u64;
+            channel.set_duty_hw(duty as u32);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{Ordering, compiler_fence},
+};
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use esp_hal::{
+    Blocking,
+    gpio::{AnyPin, Level, NoPin, Output, OutputConfig, OutputSignal},
+    handler,
+    interrupt::Priority,
+    peripherals::RMT,
+    ram,
+    rmt::{Channel, Error, PulseCode, Rmt, TxChannelConfig, TxChannelCreator},
+    time::Rate,
+};
+
+use lamp_core::{
+    config::{StripConfig, Timings},
+    output::{LedDriver, Pixel, StripLayout},
+};
+
+use crate::flash::FLASH_GATE;
+
+// Pulse codes in the RAM block of a channel and the offset of the RAM of channel 0 from the
+// registers of the peripheral, see the RMT chapter of the ESP32-C3 technical reference manual
+const CHANNEL_RAM: usize = 48;
+const RAM_OFFSET: usize = 0x400;
+// Codes refilled at a time, the RMT sends the other half of the block meanwhile
+const HALF_RAM: usize = CHANNEL_RAM / 2;
+// a code of zero length ends the transmission
+const END_CODE: u32 = 0;
+
+// frame on its way through a channel, the task of the channel owns it while the interrupts
+// of the channel are off and the interrupt handler while they are on
+struct StreamSlot(UnsafeCell<Option<Stream>>);
+
+// SAFETY: the task and the interrupt handler take turns, see above
+unsafe impl Sync for StreamSlot {}
+
+static STREAMS: [StreamSlot; 2] = [const { StreamSlot(UnsafeCell::new(None)) }; 2];
+// outcome of the last frame of a channel
+static SENT: [Signal<CriticalSectionRawMutex, Result<(), RmtError>>; 2] =
+    [const { Signal::new() }; 2];
+
+#[derive(Debug)]
+pub enum RmtError {
+    /// The RMT reported an error while sending.
+    Transmission,
+    /// The interrupt was late to refill the channel RAM and the RMT sent stale codes.
+    Underrun,
+}
+
+/// One of the two TX channels of the RMT peripheral, each can drive a strip.
+pub enum RmtChannel {
+    Ch0(Channel<Blocking, 0>),
+    Ch1(Channel<Blocking, 1>),
+}
+
+impl RmtChannel {
+    /// Clock of the RMT peripheral the pulse times are counted in.
+    pub const CLOCK: Rate = Rate::from_mhz(80);
+
+    /// Sets up the RMT peripheral with the interrupt handler that refills the channels,
+    /// the channels drive no pin until a driver is made with them.
+    pub fn channels(rmt: RMT) -> Result<[RmtChannel; 2], Error> {
+        let mut rmt = Rmt::new(rmt, Self::CLOCK)?;
+        rmt.set_interrupt_handler(refill_streams);
+        let config = TxChannelConfig::default().with_clk_divider(1);
+        Ok([
+            rmt.channel0.configure(NoPin, config)?.into(),
+            rmt.channel1.configure(NoPin, config)?.into(),
+        ])
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            RmtChannel::Ch0(_) => 0,
+            RmtChannel::Ch1(_) => 1,
+        }
+    }
+
+    // routes the output of the channel to GPIO `pin`, which must not be used elsewhere
+    unsafe fn connect(&self, pin: u8) {
+        let signal = match self {
+            RmtChannel::Ch0(_) => OutputSignal::RMT_SIG_0,
+            RmtChannel::Ch1(_) => OutputSignal::RMT_SIG_1,
+        };
+        let pin = Output::new(
+            unsafe { AnyPin::steal(pin) },
+            Level::Low,
+            OutputConfig::default(),
+        );
+        signal.connect_to(pin.into_peripheral_output());
+    }
+
+    /// Sends the whole stream as a single transmission and returns it once the RMT is done.
+    async fn transmit(&mut self, mut stream: Stream) -> (Stream, Result<(), RmtError>) {
+        let index = self.index();
+        stream.next = 0;
+        stream.half = 0;
+        stream.fill(index, 0, CHANNEL_RAM);
+
+        SENT[index].reset();
+        // SAFETY: the interrupts of the channel are off, so the handler leaves the slot alone
+        unsafe { *STREAMS[index].0.get() = Some(stream) };
+        compiler_fence(Ordering::SeqCst);
+        start(index);
+
+        let result = SENT[index].wait().await;
+        compiler_fence(Ordering::SeqCst);
+        // SAFETY: the handler turned the interrupts of the channel off before it signalled
+        let stream = unsafe { (*STREAMS[index].0.get()).take() }
+            .expect("because the slot is only emptied here");
+        (stream, result)
+    }
+}
+
+impl From<Channel<Blocking, 0>> for RmtChannel {
+    fn from(channel: Channel<Blocking, 0>) -> Self {
+        RmtChannel::Ch0(channel)
+    }
+}
+
+impl From<Channel<Blocking, 1>> for RmtChannel {
+    fn from(channel: Channel<Blocking, 1>) -> Self {
+        RmtChannel::Ch1(channel)
+    }
+}
+
+/// Pulse codes of a frame, made from its bytes as the channel RAM is refilled.
+struct Stream {
+    bytes: Vec<u8>,
+    bit_codes: [u32; 2],
+    reset_code: u32,
+    // next code to write, the bits of `bytes` are followed by the reset code and end markers
+    next: usize,
+    // half of the channel RAM that is refilled next
+    half: usize,
+}
+
+impl Stream {
+    #[ram]
+    fn code(&self, index: usize) -> u32 {
+        match self.bytes.get(index / 8) {
+            Some(byte) => self.bit_codes[((byte >> (7 - index % 8)) & 1) as usize],
+            None if index == self.bytes.len() * 8 => self.reset_code,
+            None => END_CODE,
+        }
+    }
+
+    // writes the next `len` codes to the RAM of `channel` from code `offset` on
+    #[ram]
+    fn fill(&mut self, channel: usize, offset: usize, len: usize) {
+        let ram = (RMT::PTR as usize + RAM_OFFSET + channel * CHANNEL_RAM * 4) as *mut u32;
+        for i in offset..offset + len {
+            // SAFETY: the codes stay within the RAM block of the channel
+            unsafe { ram.add(i).write_volatile(self.code(self.next)) };
+            self.next += 1;
+        }
+    }
+}
+
+// sends the channel RAM, which holds the first codes of the stream,
+// the interrupt refills each half once it was sent
+fn start(channel: usize) {
+    let rmt = RMT::regs();
+    let ch = channel as u8;
+    rmt.int_clr().write(|w| {
+        w.ch_tx_end(ch).set_bit();
+        w.ch_tx_err(ch).set_bit();
+        w.ch_tx_thr_event(ch).set_bit()
+    });
+    rmt.ch_tx_lim(channel).modify(|_, w| unsafe {
+        w.tx_loop_cnt_en().clear_bit();
+        w.tx_lim().bits(HALF_RAM as u16)
+    });
+    rmt.ch_tx_conf0(channel).modify(|_, w| unsafe {
+        w.tx_conti_mode().clear_bit();
+        w.mem_tx_wrap_en().set_bit();
+        w.mem_size().bits(1)
+    });
+    rmt.ref_cnt_rst().write(|w| unsafe { w.bits(1 << channel) });
+    update(channel);
+
+    rmt.int_ena().modify(|_, w| {
+        w.ch_tx_end(ch).set_bit();
+        w.ch_tx_err(ch).set_bit();
+        w.ch_tx_thr_event(ch).set_bit()
+    });
+    rmt.ch_tx_conf0(channel).modify(|_, w| {
+        w.mem_rd_rst().set_bit();
+        w.apb_mem_rst().set_bit();
+        w.tx_start().set_bit()
+    });
+    update(channel);
+}
+
+#[ram]
+fn update(channel: usize) {
+    RMT::regs()
+        .ch_tx_conf0(channel)
+        .modify(|_, w| w.conf_update().set_bit());
+}
+
+// the next half has to be written before the RMT gets to it, 24 codes or 30 µs at 800 kHz,
+// so the refill runs from RAM and does not wait for the flash cache
+#[handler(priority = Priority::max())]
+#[ram]
+fn refill_streams() {
+    refill(0);
+    refill(1);
+}
+
+#[ram]
+fn refill(channel: usize) {
+    let rmt = RMT::regs();
+    let ch = channel as u8;
+    // only enabled events are set, so the channel is sending and the handler owns its slot
+    let events = rmt.int_st().read();
+    let done = events.ch_tx_end(ch).bit();
+    let error = events.ch_tx_err(ch).bit();
+    if !done && !error && !events.ch_tx_thr_event(ch).bit() {
+        return;
+    }
+    // SAFETY: the interrupts of the channel are on, so the task leaves the slot alone
+    let Some(stream) = (unsafe { &mut *STREAMS[channel].0.get() }) else {
+        return;
+    };
+
+    if error {
+        finish(channel, Err(RmtError::Transmission));
+    } else if done {
+        finish(channel, Ok(()));
+    } else {
+        rmt.int_clr().write(|w| w.ch_tx_thr_event(ch).set_bit());
+        let half = stream.half;
+        stream.fill(channel, half * HALF_RAM, HALF_RAM);
+        stream.half ^= 1;
+
+        // the RMT sends the other half meanwhile, once it got to this one
+        // it has sent codes of the previous round instead of the ones just written
+        let read = rmt.ch_tx_status(channel).read().mem_raddr_ex().bits() as usize;
+        if read % CHANNEL_RAM / HALF_RAM == half {
+            rmt.ch_tx_conf0(channel)
+                .modify(|_, w| w.tx_stop().set_bit());
+            update(channel);
+            finish(channel, Err(RmtError::Underrun));
+        }
+    }
+}
+
+// turns the interrupts of the channel off, which hands the stream back to the task
+#[ram]
+fn finish(channel: usize, result: Result<(), RmtError>) {
+    let rmt = RMT::regs();
+    let ch = channel as u8;
+    rmt.int_ena().modify(|_, w| {
+        w.ch_tx_end(ch).clear_bit();
+        w.ch_tx_err(ch).clear_bit();
+        w.ch_tx_thr_event(ch).clear_bit()
+    });
+    rmt.int_clr().write(|w| {
+        w.ch_tx_end(ch).set_bit();
+        w.ch_tx_err(ch).set_bit();
+        w.ch_tx_thr_event(ch).set_bit()
+    });
+    SENT[channel].signal(result);
+}
+
+/// Driver for WS281x and SK6812 strips, whose single data line is timed by the RMT peripheral.
+///
+/// A frame is sent as one transmission that ends with the reset time of the strip.
+/// It is longer than the channel RAM, so the RMT interrupt refills one half of the RAM
+/// while the other one is sent, and the task only wakes up once the frame is done.
+pub struct RmtDriver {
+    channel: RmtChannel,
+    pin: u8,
+    num_leds: usize,
+    layout: StripLayout,
+    bit_codes: [u32; 2],
+    reset_code: u32,
+    // wire bytes of the frame, kept for the next one
+    bytes: Vec<u8>,
+}
+
+impl RmtDriver {
+    /// Driver for the strip of `config` on `channel`.
+    ///
+    /// # Safety
+    ///
+    /// The pin of `config` has to exist on the chip and must not be used anywhere else,
+    /// except by a driver that gave it up.
+    pub unsafe fn new(channel: RmtChannel, config: &StripConfig, layout: StripLayout) -> Self {
+        unsafe { channel.connect(config.pin) };
+        let (bit_codes, reset_code) = pulse_codes(&config.timings);
+        Self {
+            channel,
+            pin: config.pin,
+            num_leds: config.num_leds,
+            layout,
+            bit_codes,
+            reset_code,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Moves the strip to the pin, length and timings of `config`.
+    /// The previous pin is released unless it is one of `pins` still driven by a strip.
+    ///
+    /// # Safety
+    ///
+    /// See [`RmtDriver::new`], the current pin of the driver may be reused.
+    pub unsafe fn reconfigure(&mut self, config: &StripConfig, pins: &[u8]) {
+        if !pins.contains(&self.pin) {
+            unsafe { release(self.pin) };
+        }
+        unsafe { self.channel.connect(config.pin) };
+
+        self.pin = config.pin;
+        self.num_leds = config.num_leds;
+        (self.bit_codes, self.reset_code) = pulse_codes(&config.timings);
+    }
+
+    /// Stops driving the data line of the strip, unless it is one of `pins` still driven by a strip,
+    /// and gives back the channel for another strip.
+    pub fn release(self, pins: &[u8]) -> RmtChannel {
+        if !pins.contains(&self.pin) {
+            // SAFETY: the pin was owned by the driver
+            unsafe { release(self.pin) };
+        }
+        self.channel
+    }
+
+    async fn send_pixels(&mut self, pixels: impl Iterator<Item = Pixel>) -> Result<(), RmtError> {
+        let mut bytes = core::mem::take(&mut self.bytes);
+        bytes.clear();
+        for pixel in pixels {
+            bytes.extend(self.layout.encode(pixel));
+        }
+
+        let stream = Stream {
+            bytes,
+            bit_codes: self.bit_codes,
+            reset_code: self.reset_code,
+            next: 0,
+            half: 0,
+        };
+        let (stream, result) = FLASH_GATE.frame(self.channel.transmit(stream)).await;
+        self.bytes = stream.bytes;
+        result
+    }
+}
+
+impl LedDriver for RmtDriver {
+    type Error = RmtError;
+
+    fn num_leds(&self) -> usize {
+        self.num_leds
+    }
+
+    fn layout(&self) -> StripLayout {
+        self.layout
+    }
+
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), RmtError> {
+        self.send_pixels(frame.iter().copied().take(self.num_leds))
+            .await
+    }
+}
+
+// pulse codes of a zero and a one bit, and of the reset signal
+fn pulse_codes(timings: &Timings) -> ([u32; 2], u32) {
+    // the RMT clock ticks every 12.5 ns
+    let ticks = |nanos: u16| (nanos as u32 * 2 / 25) as u16;
+    let bit_codes = [
+        PulseCode::new(
+            Level::High,
+            ticks(timings.t0h),
+            Level::Low,
+            ticks(timings.t0l),
+        ),
+        PulseCode::new(
+            Level::High,
+            ticks(timings.t1h),
+            Level::Low,
+            ticks(timings.t1l),
+        ),
+    ];
+    // held low for the reset time, split over both halves of the code
+    let reset_code = PulseCode::new(
+        Level::Low,
+        timings.reset * 40,
+        Level::Low,
+        timings.reset * 40,
+    );
+    (bit_codes, reset_code)
+}
+
+// the channel signal stays routed to a pin it no longer drives, so it is taken back as a plain output
+unsafe fn release(pin: u8) {
+    let _ = Output::new(
+        unsafe { AnyPin::steal(pin) },
+        Level::Low,
+        OutputConfig::default(),
+    );
+}
@@ -1,46 +1,171 @@
+use alloc::{string::String, vec::Vec};
+use core::cell::RefCell;
+
 use embassy_futures::select::{Either, select};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
-use embassy_time::{Duration, Timer};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    channel::Channel,
+};
+use embassy_time::Timer;
 
-use crate::{server::LedRequest, types::Color};
+use lamp_core::{
+    config::{DeviceConfig, StripConfig},
+    http::{LedRequest, Target},
+    output::{BrightnessCurve, ColorCorrection, StripLayout},
+    runner::{LedRunner, Segment},
+};
 
 use super::{
-    controller::LedController,
-    effects::{DaylightCycle, EffectEnum, EffectStatus, MoveTo},
+    AnyDriver,
+    rmt::{RmtChannel, RmtDriver},
 };
+use crate::clock::WallClock;
+
+/// Commands for the LED task of a strip.
+pub enum StripCommand {
+    /// Start the effect of the request on the named segment, or on the whole strip.
+    Request(Option<String>, LedRequest),
+    /// Move the strip to a new configuration, `None` turns it off.
+    /// `pins` are the data pins of all strips of the new configuration,
+    /// a pin given up by this strip is only released when no other strip takes it over.
+    Configure {
+        strip: Option<StripConfig>,
+        pins: Vec<u8>,
+        brightness_curve: BrightnessCurve,
+    },
+    /// Move running cycles to the wall time after the clock of the lamp was set.
+    TimeChanged,
+}
+
+pub type LedChannel = Channel<CriticalSectionRawMutex, StripCommand, 4>;
+
+/// Configuration the strips currently run with.
+pub type SharedConfig = Mutex<CriticalSectionRawMutex, RefCell<DeviceConfig>>;
+
+/// Everything the server needs to reach the LED task of a strip.
+pub struct StripHandle {
+    pub commands: LedChannel,
+    pub segments: Vec<Segment>,
+}
+
+impl StripHandle {
+    pub fn new(segments: Vec<Segment>) -> Self {
+        Self {
+            commands: Channel::new(),
+            segments,
+        }
+    }
+
+    pub fn has_segment(&self, name: &str) -> bool {
+        self.segments.iter().any(|segment| segment.name == name)
+    }
+}
 
-pub type LedSignal = Signal<CriticalSectionRawMutex, LedRequest>;
+/// Strips requests to `target` apply to, only those that have the segment when one is named.
+pub fn targeted<'s>(
+    strips: &'s [StripHandle],
+    target: &'s Target,
+) -> impl Iterator<Item = &'s StripHandle> {
+    strips
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| target.strip.includes(*index))
+        .map(|(_, strip)| strip)
+        .filter(|strip| match &target.segment {
+            Some(segment) => strip.has_segment(segment),
+            None => true,
+        })
+}
 
-#[embassy_executor::task]
-pub async fn run_leds(mut controller: LedController, led_signal: &'static LedSignal) {
-    let mut current_effect: EffectEnum = MoveTo::new(
-        Color::new(0, 0, 0),
-        Color::new(255, 244, 200),
-        Duration::from_secs(10),
-    )
-    .into();
+/// Whether any strip takes requests to `target`.
+pub fn exists(strips: &[StripHandle], target: &Target) -> bool {
+    target.strip.fits(strips.len()) && targeted(strips, target).next().is_some()
+}
+
+// one task for every strip, a strip without a driver is set up on `channel`
+// by the first configuration, and gives its channel back there when turned off
+#[embassy_executor::task(pool_size = 2)]
+pub async fn run_leds(
+    driver: Option<AnyDriver>,
+    channel: Option<RmtChannel>,
+    layout: StripLayout,
+    correction: ColorCorrection,
+    strip: &'static StripHandle,
+    clock: &'static WallClock,
+) {
+    // the strip stays dark until the first request
+    let start = |driver: AnyDriver, correction: &ColorCorrection| {
+        LedRunner::new(driver, correction.clone(), strip.segments.clone())
+    };
+    let mut correction = correction;
+    let mut channel = channel;
+    let mut runner = driver.map(|driver| start(driver, &correction));
 
     loop {
-        // update LEDs according to effect
-        let (current_color, current_status) = current_effect.step();
-        let _ = controller.send_color(current_color).await;
+        // update LEDs according to effects
+        let timeout = match &mut runner {
+            Some(runner) => runner.update(clock).await,
+            None => None,
+        };
 
         // wait either for new command or for a delay till next LED update
-        let signal = match current_status {
-            EffectStatus::InProgress(timeout) => {
-                select(led_signal.wait(), Timer::after(timeout)).await
-            }
-            EffectStatus::Finished => Either::First(led_signal.wait().await),
+        let received = match timeout {
+            Some(timeout) => select(strip.commands.receive(), Timer::after(timeout)).await,
+            None => Either::First(strip.commands.receive().await),
         };
 
-        // if we got command then accept new effect
-        if let Either::First(command) = signal {
-            current_effect = match command {
-                LedRequest::Set(color, duration) => {
-                    MoveTo::new(current_color, color, duration).into()
+        let Either::First(command) = received else {
+            continue;
+        };
+        if let StripCommand::Configure {
+            brightness_curve, ..
+        } = &command
+        {
+            correction = ColorCorrection::new(*brightness_curve);
+            if let Some(runner) = &mut runner {
+                runner.set_correction(correction.clone());
+            }
+        }
+        match (command, &mut runner) {
+            // if we got command then accept new effect
+            (StripCommand::Request(segment, request), Some(runner)) => {
+                runner.apply(request, segment.as_deref(), clock);
+            }
+            (StripCommand::Request(..), None) => {}
+            (StripCommand::TimeChanged, Some(runner)) => runner.time_changed(clock),
+            (StripCommand::TimeChanged, None) => {}
+            // SAFETY: the task owns the pins of the strip,
+            // the server only sends configurations with pins of `STRIP_PINS` no other strip uses
+            (
+                StripCommand::Configure {
+                    strip: Some(config),
+                    pins,
+                    ..
+                },
+                Some(running),
+            ) => {
+                running.reconfigure(|driver| unsafe { driver.reconfigure(&config, &pins) });
+            }
+            (
+                StripCommand::Configure {
+                    strip: Some(config),
+                    ..
+                },
+                None,
+            ) => {
+                if let Some(channel) = channel.take() {
+                    let driver = unsafe { RmtDriver::new(channel, &config, layout) };
+                    runner = Some(start(driver.into(), &correction));
                 }
-                LedRequest::DaylightCycle(color, current_time, ranges) => {
-                    DaylightCycle::new(current_color, color, current_time, ranges).into()
+            }
+            (
+                StripCommand::Configure {
+                    strip: None, pins, ..
+                },
+                _,
+            ) => {
+                if let Some(runner) = runner.take() {
+                    channel = runner.into_driver().release(&pins);
                 }
             }
         }
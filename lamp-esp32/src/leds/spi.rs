@@ -0,0 +1,119 @@
+use alloc::vec::Vec;
+
+use esp_hal::{
+    Async,
+    dma::{DmaBufError, DmaChannel0, DmaRxBuf, DmaTxBuf},
+    dma_buffers,
+    gpio::AnyPin,
+    peripheral::Peripheral,
+    peripherals::SPI2,
+    spi::{
+        Error,
+        master::{Config, ConfigError, Spi, SpiDmaBus},
+    },
+    time::Rate,
+};
+
+use lamp_core::output::{LedDriver, Pixel, StripLayout};
+
+// APA102 and SK9822 take clocks of up to a few MHz over longer strips
+const CLOCK: Rate = Rate::from_mhz(4);
+
+// Zero bytes before the first LED
+const START_FRAME: usize = 4;
+
+// First byte of every LED, three marker bits and the global brightness set to full
+const LED_HEADER: u8 = 0xFF;
+
+// Bytes the DMA sends at once, longer frames go out in several chunks,
+// the strip waits for the clock in between
+const DMA_CHUNK: usize = 1024;
+
+#[derive(Debug)]
+pub enum SpiSetupError {
+    Config(ConfigError),
+    Buffer(DmaBufError),
+}
+
+/// Driver for APA102 and SK9822 strips, which have a clock line next to the data line.
+///
+/// Frames go out by DMA while the executor keeps running.
+pub struct SpiDriver {
+    spi: SpiDmaBus<'static, Async>,
+    num_leds: usize,
+    layout: StripLayout,
+    data: Vec<u8>,
+}
+
+impl SpiDriver {
+    /// These strips have no white LED, a white channel in `layout` is ignored.
+    /// The DMA buffers are static, so there is only ever one driver.
+    pub fn new(
+        peripheral: impl Peripheral<P = SPI2> + 'static,
+        dma_channel: impl Peripheral<P = DmaChannel0> + 'static,
+        clock_pin: AnyPin,
+        data_pin: AnyPin,
+        num_leds: usize,
+        layout: StripLayout,
+    ) -> Result<Self, SpiSetupError> {
+        // nothing is read back, the receive buffer only satisfies the bus
+        #[allow(clippy::manual_div_ceil)] // within the macro of esp-hal
+        let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = dma_buffers!(4, DMA_CHUNK);
+        let rx = DmaRxBuf::new(rx_descriptors, rx_buffer).map_err(SpiSetupError::Buffer)?;
+        let tx = DmaTxBuf::new(tx_descriptors, tx_buffer).map_err(SpiSetupError::Buffer)?;
+
+        let spi = Spi::new(peripheral, Config::default().with_frequency(CLOCK))
+            .map_err(SpiSetupError::Config)?
+            .with_sck(clock_pin)
+            .with_mosi(data_pin)
+            .with_dma(dma_channel)
+            .with_buffers(rx, tx)
+            .into_async();
+
+        Ok(Self {
+            spi,
+            num_leds,
+            layout: StripLayout {
+                white: false,
+                ..layout
+            },
+            data: Vec::with_capacity(START_FRAME + num_leds * 4 + Self::end_frame(num_leds)),
+        })
+    }
+
+    /// The pins stay the ones the driver was created with.
+    pub fn set_num_leds(&mut self, num_leds: usize) {
+        self.num_leds = num_leds;
+    }
+
+    // Every LED delays the data by half a clock, the end frame pushes it to the last one.
+    // SK9822 additionally latches the colors on 32 zero bits.
+    fn end_frame(num_leds: usize) -> usize {
+        4 + num_leds.div_ceil(16)
+    }
+}
+
+impl LedDriver for SpiDriver {
+    type Error = Error;
+
+    fn num_leds(&self) -> usize {
+        self.num_leds
+    }
+
+    fn layout(&self) -> StripLayout {
+        self.layout
+    }
+
+    async fn send_frame(&mut self, frame: &[Pixel]) -> Result<(), Error> {
+        self.data.clear();
+        self.data.extend([0; START_FRAME]);
+        for pixel in frame.iter().copied().take(self.num_leds) {
+            self.data.push(LED_HEADER);
+            self.data.extend(self.layout.encode(pixel));
+        }
+        self.data
+            .extend(core::iter::repeat_n(0, Self::end_frame(self.num_leds)));
+
+        self.spi.write_async(&self.data).await
+    }
+}
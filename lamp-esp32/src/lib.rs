@@ -1,5 +1,8 @@
 #![no_std]
 
+extern crate alloc;
+
+pub mod clock;
+pub mod flash;
 pub mod leds;
 pub mod server;
-pub mod types;
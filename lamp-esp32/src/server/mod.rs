@@ -1,13 +1,6 @@
+pub mod sntp;
 pub mod wifi;
 
+#[allow(clippy::module_inception)]
 mod server;
 pub use server::Server;
-
-mod parse_error;
-pub use parse_error::ParseError;
-
-mod request;
-pub use request::LedRequest;
-
-mod response_builder;
-pub use response_builder::ResponseBuilder;
@@ -1,27 +1,59 @@
+use chrono::{DateTime, FixedOffset};
 use embassy_net::{Stack, tcp::TcpSocket};
 use embassy_time::{Duration, Timer};
 use embedded_io_async::Write;
 
-use crate::leds::runner::LedSignal;
+use alloc::vec::Vec;
 
-use super::{LedRequest, ResponseBuilder};
+use lamp_core::{
+    http::{ApiRequest, ParseError, ResponseBuilder, time_to_json},
+    settings::{LastRequest, Setting},
+};
+
+use crate::{
+    clock::WallClock,
+    flash::{FLASH_GATE, SharedSettings},
+    leds::{
+        StripOutput, fits_board,
+        runner::{SharedConfig, StripCommand, StripHandle, exists, targeted},
+    },
+};
 
 pub struct Server<'d, const B: usize, const W: usize> {
     rx_buffer: [u8; B],
     tx_buffer: [u8; B],
     work_buffer: [u8; W],
     stack: Stack<'d>,
-    led_signal: &'d LedSignal,
+    strips: &'d [StripHandle],
+    output: StripOutput,
+    config: &'d SharedConfig,
+    settings: &'d SharedSettings,
+    wall_clock: &'d WallClock,
 }
 
 impl<'d, const B: usize, const W: usize> Server<'d, B, W> {
-    pub fn new(stack: Stack<'d>, led_signal: &'d LedSignal) -> Self {
+    /// `strips` are the handles of all strip tasks, `config` tells how many of them are in use
+    /// and `output` which changes the first one can take.
+    /// Changes are saved to `settings` when there is a store,
+    /// `wall_clock` is set by `POST /time` and the time of cycle requests.
+    pub fn new(
+        stack: Stack<'d>,
+        strips: &'d [StripHandle],
+        output: StripOutput,
+        config: &'d SharedConfig,
+        settings: &'d SharedSettings,
+        wall_clock: &'d WallClock,
+    ) -> Self {
         Self {
             rx_buffer: [0; B],
             tx_buffer: [0; B],
             work_buffer: [0; W],
             stack,
-            led_signal,
+            strips,
+            output,
+            config,
+            settings,
+            wall_clock,
         }
     }
 
@@ -40,20 +72,95 @@ impl<'d, const B: usize, const W: usize> Server<'d, B, W> {
             };
 
             let accept_result = socket.accept((v4.address.address(), 8308)).await;
-            if let Err(_) = accept_result {
+            if accept_result.is_err() {
                 continue;
             }
 
             if let Ok(n) = socket.read(&mut self.work_buffer).await
                 && n > 0
             {
-                let parse_result = LedRequest::parse_http(&self.work_buffer[..n]);
+                let config = self.config;
+                let first_strip = config.lock(|config| config.borrow().strips[0].clone());
+                let num_strips = config.lock(|config| config.borrow().strips.len());
+                let num_tasks = self.strips.len();
+                let strips = &self.strips[..num_strips.min(num_tasks)];
+                let time_known = self.wall_clock.now().is_some();
+                let parse_result =
+                    ApiRequest::parse_http(&self.work_buffer[..n]).and_then(|request| {
+                        let valid = match &request {
+                            ApiRequest::Led(target, request) => {
+                                exists(strips, target) && (time_known || !request.needs_time())
+                            }
+                            ApiRequest::GetConfig => true,
+                            ApiRequest::SetConfig(config) => {
+                                fits_board(config, num_tasks)
+                                    && self.output.applies(&first_strip, &config.strips[0])
+                            }
+                            ApiRequest::SetWifi(_) => true,
+                            ApiRequest::GetTime => true,
+                            ApiRequest::SetTime(_) => true,
+                        };
+                        match valid {
+                            true => Ok(request),
+                            false => Err(ParseError::ValueError),
+                        }
+                    });
 
                 let mut response_builder = ResponseBuilder::new(&mut self.work_buffer);
 
                 let response = match parse_result {
-                    Ok(request) => {
-                        self.led_signal.signal(request);
+                    Ok(ApiRequest::Led(target, request)) => {
+                        if let Some(current_time) = request.current_time() {
+                            set_time(self.wall_clock, strips, current_time).await;
+                        }
+                        for strip in targeted(strips, &target) {
+                            let command =
+                                StripCommand::Request(target.segment.clone(), request.clone());
+                            strip.commands.send(command).await;
+                        }
+                        save(self.settings, &LastRequest { target, request }).await;
+                        response_builder.build_response()
+                    }
+                    Ok(ApiRequest::GetConfig) => {
+                        let json = config.lock(|config| config.borrow().to_json());
+                        response_builder.build_json_response(&json)
+                    }
+                    Ok(ApiRequest::SetConfig(new_config)) => {
+                        let pins: Vec<u8> =
+                            new_config.strips.iter().map(|strip| strip.pin).collect();
+                        for (index, strip) in self.strips.iter().enumerate() {
+                            let command = StripCommand::Configure {
+                                strip: new_config.strips.get(index).cloned(),
+                                pins: pins.clone(),
+                                brightness_curve: new_config.brightness_curve,
+                            };
+                            strip.commands.send(command).await;
+                        }
+                        save(self.settings, &new_config).await;
+                        config.lock(|config| config.replace(new_config));
+                        response_builder.build_response()
+                    }
+                    Ok(ApiRequest::SetWifi(credentials)) => {
+                        match save(self.settings, &credentials).await {
+                            true => response_builder.build_response(),
+                            // without a store the credentials would be lost
+                            false => response_builder.build_bad_request(ParseError::ValueError),
+                        }
+                    }
+                    Ok(ApiRequest::GetTime) => {
+                        let zone = self.wall_clock.zone();
+                        let json = time_to_json(self.wall_clock.now(), zone.as_ref());
+                        response_builder.build_json_response(&json)
+                    }
+                    Ok(ApiRequest::SetTime(request)) => {
+                        if let Some(zone) = request.time_zone {
+                            save(self.settings, &zone).await;
+                            self.wall_clock.set_zone(zone);
+                        }
+                        match request.time {
+                            Some(time) => set_time(self.wall_clock, strips, time).await,
+                            None => time_changed(strips).await,
+                        }
                         response_builder.build_response()
                     }
                     Err(error) => response_builder.build_bad_request(error),
@@ -66,3 +173,29 @@ impl<'d, const B: usize, const W: usize> Server<'d, B, W> {
         }
     }
 }
+
+// running effects keep going in the phase of the new time
+async fn set_time(clock: &WallClock, strips: &[StripHandle], time: DateTime<FixedOffset>) {
+    clock.set(time);
+    time_changed(strips).await;
+}
+
+async fn time_changed(strips: &[StripHandle]) {
+    for strip in strips {
+        strip.commands.send(StripCommand::TimeChanged).await;
+    }
+}
+
+// returns whether the setting was saved
+async fn save(settings: &SharedSettings, setting: &impl Setting) -> bool {
+    FLASH_GATE
+        .access(|| {
+            settings.lock(|settings| {
+                settings
+                    .borrow_mut()
+                    .as_mut()
+                    .is_some_and(|settings| settings.save(setting).is_ok())
+            })
+        })
+        .await
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use embassy_net::{
+    IpEndpoint, Stack,
+    dns::DnsQueryType,
+    udp::{PacketMetadata, UdpSocket},
+};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+
+use lamp_core::sntp::{PACKET_SIZE, PORT, SntpError, SntpRequest};
+
+use crate::{
+    clock::WallClock,
+    leds::runner::{StripCommand, StripHandle},
+};
+
+// server asked for the time, set NTP_SERVER at build time to use another one,
+// e.g. the address of a local server
+const NTP_SERVER: &str = match option_env!("NTP_SERVER") {
+    Some(server) => server,
+    None => "pool.ntp.org",
+};
+// the chip clock drifts by a few seconds a day at most
+const SYNC_PERIOD: Duration = Duration::from_secs(60 * 60);
+const RETRY_PERIOD: Duration = Duration::from_secs(30);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keeps `clock` set to the time of the NTP server and moves the running cycles of `strips` along.
+#[embassy_executor::task]
+pub async fn sync_time(
+    stack: Stack<'static>,
+    clock: &'static WallClock,
+    strips: &'static [StripHandle],
+) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0; PACKET_SIZE];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; PACKET_SIZE];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    // any free local port
+    if socket.bind(0).is_err() {
+        return;
+    }
+
+    loop {
+        stack.wait_config_up().await;
+
+        let period = match sync(stack, &socket).await {
+            Some(utc) => {
+                clock.set_utc(utc);
+                for strip in strips {
+                    strip.commands.send(StripCommand::TimeChanged).await;
+                }
+                SYNC_PERIOD
+            }
+            None => RETRY_PERIOD,
+        };
+        Timer::after(period).await;
+    }
+}
+
+async fn sync(stack: Stack<'static>, socket: &UdpSocket<'_>) -> Option<DateTime<Utc>> {
+    let address = stack
+        .dns_query(NTP_SERVER, DnsQueryType::A)
+        .await
+        .ok()?
+        .first()
+        .copied()?;
+    let server = IpEndpoint::new(address, PORT);
+
+    let sent = Instant::now();
+    let request = SntpRequest::new(sent.as_ticks());
+    socket.send_to(&request.packet(), server).await.ok()?;
+
+    let mut response = [0; PACKET_SIZE];
+    with_timeout(RESPONSE_TIMEOUT, async {
+        loop {
+            let (n, meta) = socket.recv_from(&mut response).await.ok()?;
+            if meta.endpoint != server {
+                continue;
+            }
+            match request.parse_response(&response[..n], sent.elapsed()) {
+                // a late answer to an earlier request
+                Err(SntpError::Unexpected) => continue,
+                result => break result.ok(),
+            }
+        }
+    })
+    .await
+    .ok()?
+}
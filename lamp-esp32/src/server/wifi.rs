@@ -28,7 +28,7 @@ pub async fn connection(
             controller.start_async().await.unwrap();
         }
 
-        if let Err(_) = controller.connect_async().await {
+        if controller.connect_async().await.is_err() {
             Timer::after(Duration::from_millis(5000)).await
         }
     }
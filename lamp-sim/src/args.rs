@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use embassy_time::Duration;
+
+pub const USAGE: &str = "\
+usage: lamp-sim [options] <request.json[@seconds]>...
+
+Simulates the lamp effects started by the given request bodies.
+Each request is applied at the given number of seconds from the start (default 0).
+
+options:
+    --hours <hours>       simulated time span (default 24)
+    --step <seconds>      time between recorded samples (default 60)
+    --leds <count>        number of LEDs on the strip (default 12)
+    --csv <path>          write the color timeline as CSV
+    --png <path>          write the color timeline as an image";
+
+pub struct ScheduledRequest {
+    pub path: PathBuf,
+    pub offset: Duration,
+}
+
+pub struct Args {
+    pub requests: Vec<ScheduledRequest>,
+    pub duration: Duration,
+    pub step: Duration,
+    pub num_leds: usize,
+    pub csv: Option<PathBuf>,
+    pub png: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self {
+            requests: Vec::new(),
+            duration: Duration::from_secs(24 * 60 * 60),
+            step: Duration::from_secs(60),
+            num_leds: 12,
+            csv: None,
+            png: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--hours" => parsed.duration = Duration::from_secs(parse_num(&value()?)? * 60 * 60),
+                "--step" => parsed.step = Duration::from_secs(parse_num(&value()?)?),
+                "--leds" => parsed.num_leds = parse_num(&value()?)? as usize,
+                "--csv" => parsed.csv = Some(value()?.into()),
+                "--png" => parsed.png = Some(value()?.into()),
+                _ if arg.starts_with("--") => Err(format!("unknown option {arg}"))?,
+                _ => parsed.requests.push(Self::parse_request(&arg)?),
+            }
+        }
+
+        if parsed.requests.is_empty() {
+            Err("no requests given")?
+        }
+        if parsed.step.as_ticks() == 0 || parsed.num_leds == 0 {
+            Err("--step and --leds must be positive")?
+        }
+
+        Ok(parsed)
+    }
+
+    fn parse_request(arg: &str) -> Result<ScheduledRequest, String> {
+        let (path, offset) = match arg.rsplit_once('@') {
+            Some((path, secs)) => (path, Duration::from_secs(parse_num(secs)?)),
+            None => (arg, Duration::from_secs(0)),
+        };
+
+        Ok(ScheduledRequest {
+            path: path.into(),
+            offset,
+        })
+    }
+}
+
+fn parse_num(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got {value}"))
+}
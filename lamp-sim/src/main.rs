@@ -0,0 +1,50 @@
+mod args;
+mod output;
+mod simulation;
+
+use std::{fs, process::ExitCode};
+
+use lamp_core::http::LedRequest;
+
+use args::{Args, USAGE};
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Args) -> Result<(), String> {
+    args.requests.sort_by_key(|request| request.offset);
+
+    let mut requests = Vec::with_capacity(args.requests.len());
+    for scheduled in &args.requests {
+        let path = scheduled.path.display();
+        let body = fs::read_to_string(&scheduled.path).map_err(|e| format!("{path}: {e}"))?;
+        let request = LedRequest::parse_json(&body).map_err(|e| format!("{path}: {e:?}"))?;
+        requests.push((scheduled.offset, request));
+    }
+
+    let timeline = simulation::simulate(requests, args.num_leds, args.duration, args.step);
+
+    if let Some(path) = &args.csv {
+        output::write_csv(&timeline, path).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    if let Some(path) = &args.png {
+        output::write_png(&timeline, path).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::simulation::Timeline;
+
+// Height of a single LED in the rendered image
+const LED_PIXELS: usize = 8;
+
+/// Writes one `seconds,led,r,g,b` row per LED and sample.
+pub fn write_csv(timeline: &Timeline, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "seconds,led,r,g,b")?;
+    for (sample, frame) in timeline.frames.iter().enumerate() {
+        let secs = timeline.step.as_secs() * sample as u64;
+        for (led, color) in frame.iter().enumerate() {
+            let [r, g, b] = color.rgb();
+            writeln!(out, "{secs},{led},{r},{g},{b}")?;
+        }
+    }
+
+    out.flush()
+}
+
+/// Renders the timeline with time going left to right and one band of pixels per LED.
+pub fn write_png(timeline: &Timeline, path: &Path) -> io::Result<()> {
+    let width = timeline.frames.len();
+    let num_leds = timeline.frames.first().map_or(0, Vec::len);
+    let height = num_leds * LED_PIXELS;
+
+    let mut data = vec![0; width * height * 3];
+    for (x, frame) in timeline.frames.iter().enumerate() {
+        for (led, color) in frame.iter().enumerate() {
+            for y in led * LED_PIXELS..(led + 1) * LED_PIXELS {
+                let idx = (y * width + x) * 3;
+                data[idx..idx + 3].copy_from_slice(&color.rgb());
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+
+    Ok(())
+}
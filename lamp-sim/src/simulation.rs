@@ -0,0 +1,109 @@
+use embassy_time::{Duration, Instant};
+use lamp_core::{
+    effects::{EffectEnum, EffectStatus},
+    http::LedRequest,
+    types::{
+        Color,
+        clock::{Clock, ManualClock},
+    },
+};
+
+/// Colors shown by the strip, sampled every `step`.
+pub struct Timeline {
+    pub step: Duration,
+    pub frames: Vec<Vec<Color>>,
+}
+
+/// Runs the requests through the effects the same way the firmware runner does,
+/// fast-forwarding the clock from one LED update to the next.
+///
+/// `requests` have to be ordered by their offset from the start.
+pub fn simulate(
+    requests: Vec<(Duration, LedRequest)>,
+    num_leds: usize,
+    duration: Duration,
+    step: Duration,
+) -> Timeline {
+    let clock = ManualClock::default();
+    let start = clock.now();
+    let mut requests = requests.into_iter().peekable();
+
+    let mut frame = vec![Color::black(); num_leds];
+    let mut effect: Option<EffectEnum> = None;
+    let mut next_update: Option<Instant> = None;
+
+    let samples = duration.as_ticks() / step.as_ticks();
+    let mut frames = Vec::with_capacity(samples as usize + 1);
+
+    for sample in 0..=samples {
+        let sample_at = start + Duration::from_ticks(step.as_ticks() * sample);
+
+        loop {
+            let request_at = requests.peek().map(|(offset, _)| start + *offset);
+            let event_at = match request_at.into_iter().chain(next_update).min() {
+                Some(at) if at <= sample_at => at,
+                _ => break,
+            };
+
+            clock.set(event_at);
+
+            // a new request replaces the running effect
+            if request_at == Some(event_at)
+                && let Some((_, request)) = requests.next()
+            {
+                // the time of a cycle request sets the clock, like on the lamp
+                if let Some(time) = request.current_time() {
+                    clock.set_wall_time(time);
+                }
+                let current_color = frame.first().copied().unwrap_or_default();
+                effect = Some(EffectEnum::from_request(request, current_color, &clock));
+            }
+
+            next_update =
+                effect
+                    .as_mut()
+                    .and_then(|effect| match effect.step(&clock, &mut frame) {
+                        EffectStatus::InProgress(timeout) => Some(event_at + timeout),
+                        EffectStatus::Finished => None,
+                    });
+        }
+
+        clock.set(sample_at);
+        frames.push(frame.clone());
+    }
+
+    Timeline { step, frames }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_to_requested_color() {
+        let request = LedRequest::parse_json(
+            r#"{"type": "set", "color": [200, 100, 0], "duration": 600000}"#,
+        )
+        .unwrap();
+
+        let timeline = simulate(
+            vec![(Duration::from_secs(60), request)],
+            3,
+            Duration::from_secs(20 * 60),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(timeline.frames.len(), 21);
+        assert_eq!(timeline.frames[0][0].rgb(), [0, 0, 0]);
+        assert_eq!(timeline.frames[6][2].rgb(), [100, 50, 0]);
+        assert_eq!(timeline.frames[11][1].rgb(), [200, 100, 0]);
+        assert_eq!(timeline.frames[20][0].rgb(), [200, 100, 0]);
+
+        let reds: Vec<_> = timeline
+            .frames
+            .iter()
+            .map(|frame| frame[0].rgb()[0])
+            .collect();
+        assert!(reds.is_sorted());
+    }
+}